@@ -11,12 +11,16 @@ pub use window_backend::{Backend, Color};
 use crate::{ui::Hitbox, world::GamePos, Game};
 
 pub const TEXT_SIZE: usize = 16;
+/// Approximate width of a character of the UI font
+pub const CHAR_WIDTH: f32 = TEXT_SIZE as f32 * 0.55;
+pub const LINE_HEIGHT: f32 = TEXT_SIZE as f32 + 3.0;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Colors {
 	Background = 0,
 	Crystal,
 	Ore,
+	Metal,
 	Worker,
 	NoPower,
 	Chunk,
@@ -25,6 +29,17 @@ pub enum Colors {
 	Cursor,
 	Button,
 	Black,
+	Disabled,
+	Panel,
+	Text,
+	Ghost,
+	Invalid,
+	PowerCoverage,
+	InputPort,
+	OutputPort,
+	Fog,
+	CaveIn,
+	Energy,
 }
 impl Colors {
 	pub fn num(self) -> i32 {
@@ -37,6 +52,7 @@ impl From<Colors> for Color {
 			Colors::Background => Color::rgb(128, 128, 128),
 			Colors::Crystal => Color::rgb(87, 255, 23),
 			Colors::Ore => Color::rgb(165, 110, 31),
+			Colors::Metal => Color::rgb(170, 185, 205),
 			Colors::Worker => Color::rgb(250, 191, 15),
 			Colors::NoPower => Color::rgba(0, 0, 0, 140),
 			Colors::Chunk => Color::rgb(255, 0, 0),
@@ -45,6 +61,17 @@ impl From<Colors> for Color {
 			Colors::Cursor => Color::rgba(180, 180, 255, 180),
 			Colors::Button => Color::rgb(128, 128, 128),
 			Colors::Black => Color::rgb(0, 0, 0),
+			Colors::Disabled => Color::rgb(70, 70, 70),
+			Colors::Panel => Color::rgba(0, 0, 0, 180),
+			Colors::Text => Color::rgb(230, 230, 230),
+			Colors::Ghost => Color::rgba(255, 255, 255, 80),
+			Colors::Invalid => Color::rgba(255, 0, 0, 120),
+			Colors::PowerCoverage => Color::rgba(80, 160, 255, 70),
+			Colors::InputPort => Color::rgb(80, 200, 80),
+			Colors::OutputPort => Color::rgb(230, 120, 40),
+			Colors::Fog => Color::rgba(0, 0, 0, 110),
+			Colors::CaveIn => Color::rgba(255, 140, 0, 60),
+			Colors::Energy => Color::rgb(250, 220, 40),
 		}
 	}
 }
@@ -108,6 +135,14 @@ pub trait BackendStyle {
 	/// Draws a segment of an image from `tile` at `target_pos`
 	fn draw_asset<T: Into<GamePos>>(&mut self, tile: (usize, usize), target_pos: T);
 
+	/// Draws a segment of an image from `tile` stretched to the Rectangle at `target_pos`
+	fn draw_asset_scaled<T: Into<GamePos>, T2: Into<GamePos>>(
+		&mut self,
+		tile: (usize, usize),
+		target_pos: T,
+		size: T2,
+	);
+
 	/// Draw the cached background
 	fn draw_background(&mut self);
 
@@ -116,4 +151,19 @@ pub trait BackendStyle {
 
 	/// draws an asset from `tile` to the background cache at `target_pos`
 	fn draw_to_background<T: Into<GamePos>>(&mut self, tile: (usize, usize), target_pos: T);
+
+	/// Multiplies the Tile at `target_pos` in the background cache with `tint`
+	fn tint_background<T: Into<GamePos>>(&mut self, target_pos: T, tint: (u8, u8, u8));
+
+	/// Draw the Minimap cache stretched to the Rectangle at `pos`
+	fn draw_minimap<T: Into<GamePos>, T2: Into<GamePos>>(&mut self, pos: T, size: T2);
+
+	/// Sets the Color of a single Tile in the Minimap cache
+	fn draw_to_minimap(&mut self, tile: (usize, usize), color: (u8, u8, u8));
+
+	/// Reads the config file `name`. Returns `None` if it doesn't exist
+	fn load_config(name: &str) -> Option<String>;
+
+	/// Writes `content` to the config file `name`
+	fn save_config(name: &str, content: &str);
 }
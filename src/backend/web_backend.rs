@@ -150,8 +150,10 @@ fn resize() {
 pub struct Backend {
 	canvas: CanvasElement,
 	background_canvas: CanvasElement,
+	minimap_canvas: CanvasElement,
 	ctx: CanvasRenderingContext2d,
 	bg: CanvasRenderingContext2d,
+	mm: CanvasRenderingContext2d,
 	assets: ImageElement,
 	width: u32,
 	height: u32,
@@ -172,6 +174,7 @@ impl BackendStyle for Backend {
 				@{ Color::from(Colors::Background).to_css() },
 				@{ Color::from(Colors::Crystal).to_css() },
 				@{ Color::from(Colors::Ore).to_css() },
+				@{ Color::from(Colors::Metal).to_css() },
 				@{ Color::from(Colors::Worker).to_css() },
 				@{ Color::from(Colors::NoPower).to_css() },
 				@{ Color::from(Colors::Chunk).to_css() },
@@ -180,6 +183,17 @@ impl BackendStyle for Backend {
 				@{ Color::from(Colors::Cursor).to_css() },
 				@{ Color::from(Colors::Button).to_css() },
 				@{ Color::from(Colors::Black).to_css() },
+				@{ Color::from(Colors::Disabled).to_css() },
+				@{ Color::from(Colors::Panel).to_css() },
+				@{ Color::from(Colors::Text).to_css() },
+				@{ Color::from(Colors::Ghost).to_css() },
+				@{ Color::from(Colors::Invalid).to_css() },
+				@{ Color::from(Colors::PowerCoverage).to_css() },
+				@{ Color::from(Colors::InputPort).to_css() },
+				@{ Color::from(Colors::OutputPort).to_css() },
+				@{ Color::from(Colors::Fog).to_css() },
+				@{ Color::from(Colors::CaveIn).to_css() },
+				@{ Color::from(Colors::Energy).to_css() },
 			];
 		}
 
@@ -213,6 +227,15 @@ impl BackendStyle for Backend {
 		background_canvas.set_width((game.world.width() * TILE_SIZE) as u32);
 		background_canvas.set_height((game.world.height() * TILE_SIZE) as u32);
 
+		let minimap_canvas: CanvasElement = document()
+			.create_element("canvas")
+			.unwrap()
+			.try_into()
+			.unwrap();
+
+		minimap_canvas.set_width(game.world.width() as u32);
+		minimap_canvas.set_height(game.world.height() as u32);
+
 		let assets: &[u8] = include_bytes!("../../assets/assets.png");
 		let assets = base64::encode(assets);
 
@@ -229,8 +252,10 @@ impl BackendStyle for Backend {
 			BACKEND = Some(Backend {
 				ctx: canvas.get_context().unwrap(),
 				bg: background_canvas.get_context().unwrap(),
+				mm: minimap_canvas.get_context().unwrap(),
 				canvas,
 				background_canvas,
+				minimap_canvas,
 				assets: img,
 				width: 200,
 				height: 200,
@@ -357,6 +382,25 @@ impl BackendStyle for Backend {
 		).expect("Unable to draw image");
 	}
 
+	fn draw_asset_scaled<T: Into<GamePos>, T2: Into<GamePos>>(
+		&mut self,
+		(row, id): (usize, usize),
+		pos: T,
+		size: T2,
+	) {
+		let (x, y) = pos.into().into();
+		let (w, h) = size.into().into();
+
+		#[rustfmt::skip]
+		self.ctx.draw_image_s(
+			self.assets.clone(),
+			(id * TILE_SIZE) as f64, (row * TILE_SIZE) as f64,
+			TILE_SIZE as f64, TILE_SIZE as f64,
+			x, y,
+			w, h
+		).expect("Unable to draw image");
+	}
+
 	fn draw_background(&mut self) {
 		js! {
 			@{ &self.ctx }.drawImage(@{ &self.background_canvas }, 0.0, 0.0);
@@ -364,6 +408,7 @@ impl BackendStyle for Backend {
 	}
 
 	fn clear_background(&mut self) {
+		js! { @{ &self.bg }.fillStyle = "black" }
 		self.bg.fill_rect(
 			0.0,
 			0.0,
@@ -384,6 +429,45 @@ impl BackendStyle for Backend {
 			TILE_SIZE as f64, TILE_SIZE as f64
 		).expect("Unable to draw image");
 	}
+
+	fn tint_background<T: Into<GamePos>>(&mut self, pos: T, (r, g, b): (u8, u8, u8)) {
+		let (x, y) = pos.into().into();
+
+		js! {
+			@{ &self.bg }.globalCompositeOperation = "multiply";
+			@{ &self.bg }.fillStyle = @{ Color::rgb(r, g, b).to_css() };
+		}
+		self.bg.fill_rect(x, y, TILE_SIZE as f64, TILE_SIZE as f64);
+		js! { @{ &self.bg }.globalCompositeOperation = "source-over" }
+	}
+
+	fn draw_minimap<T: Into<GamePos>, T2: Into<GamePos>>(&mut self, pos: T, size: T2) {
+		let (x, y) = pos.into().into();
+		let (w, h): (f64, f64) = size.into().into();
+
+		js! {
+			@{ &self.ctx }.drawImage(@{ &self.minimap_canvas }, @{ x }, @{ y }, @{ w }, @{ h });
+		}
+	}
+
+	fn draw_to_minimap(&mut self, (x, y): (usize, usize), (r, g, b): (u8, u8, u8)) {
+		js! { @{ &self.mm }.fillStyle = @{ Color::rgb(r, g, b).to_css() } }
+		self.mm.fill_rect(x as f64, y as f64, 1.0, 1.0);
+	}
+
+	fn load_config(name: &str) -> Option<String> {
+		js! {
+			return localStorage.getItem(@{ name });
+		}
+		.try_into()
+		.unwrap_or(None)
+	}
+
+	fn save_config(name: &str, content: &str) {
+		js! {
+			localStorage.setItem(@{ name }, @{ content });
+		}
+	}
 }
 
 fn convert_key_code(key: &str) -> Option<ui::KeyCode> {
@@ -30,6 +30,7 @@ pub struct Backend<'a> {
 	font: Font,
 	assets: Vec<Vec<Sprite<'a>>>,
 	background: RenderTexture,
+	minimap: RenderTexture,
 	view: View,
 }
 
@@ -46,6 +47,10 @@ impl<'a> BackendStyle for Backend<'a> {
 		)
 		.unwrap();
 
+		let minimap =
+			RenderTexture::new(game.world.width() as u32, game.world.height() as u32, false)
+				.unwrap();
+
 		let mut backend = Backend {
 			window: RenderWindow::new(
 				VideoMode::desktop_mode(),
@@ -57,6 +62,7 @@ impl<'a> BackendStyle for Backend<'a> {
 				.expect("Unable to load Font"),
 			assets: Vec::new(),
 			background,
+			minimap,
 			view: View::default(),
 		};
 
@@ -99,10 +105,10 @@ impl<'a> BackendStyle for Backend<'a> {
 					KeyPressed {
 						code, ctrl, shift, ..
 					} => {
-						if shift && code == window::Key::Escape {
+						game.on_key_press(convert_key_code(code), shift.into(), ctrl.into());
+						if game.should_quit() {
 							break 'game_loop;
 						}
-						game.on_key_press(convert_key_code(code), shift.into(), ctrl.into());
 					}
 					KeyReleased { ctrl, shift, .. } => {
 						game.on_key_press(None, shift.into(), ctrl.into());
@@ -258,6 +264,21 @@ impl<'a> BackendStyle for Backend<'a> {
 		self.window.draw(sprite);
 	}
 
+	fn draw_asset_scaled<T: Into<GamePos>, T2: Into<GamePos>>(
+		&mut self,
+		(row, id): (usize, usize),
+		target_pos: T,
+		size: T2,
+	) {
+		let size = size.into();
+		let sprite = &mut self.assets[row][id];
+		sprite.set_position(target_pos.into());
+		sprite.set_scale((size.x / TILE_SIZE as f32, size.y / TILE_SIZE as f32));
+		self.window.draw(sprite);
+		// the Sprites are shared with draw_asset
+		sprite.set_scale((1.0, 1.0));
+	}
+
 	fn draw_background(&mut self) {
 		self.background.display();
 		let sprite = Sprite::with_texture(self.background.texture());
@@ -273,6 +294,49 @@ impl<'a> BackendStyle for Backend<'a> {
 		sprite.set_position(target_pos.into());
 		self.background.draw(sprite);
 	}
+
+	fn tint_background<T: Into<GamePos>>(&mut self, target_pos: T, (r, g, b): (u8, u8, u8)) {
+		let mut rect = RectangleShape::new();
+		rect.set_position(target_pos.into());
+		rect.set_size((TILE_SIZE as f32, TILE_SIZE as f32));
+		rect.set_fill_color(&Color::rgb(r, g, b));
+		let states = RenderStates {
+			blend_mode: BlendMode::MULTIPLY,
+			..Default::default()
+		};
+		self.background.draw_with_renderstates(&rect, states);
+	}
+
+	fn draw_minimap<T: Into<GamePos>, T2: Into<GamePos>>(&mut self, pos: T, size: T2) {
+		self.minimap.display();
+		let size = size.into();
+		let texture_size = self.minimap.size();
+		let mut sprite = Sprite::with_texture(self.minimap.texture());
+		sprite.set_position(pos.into());
+		sprite.set_scale((
+			size.x / texture_size.x as f32,
+			size.y / texture_size.y as f32,
+		));
+		self.window.draw(&sprite);
+	}
+
+	fn draw_to_minimap(&mut self, (x, y): (usize, usize), (r, g, b): (u8, u8, u8)) {
+		let mut rect = RectangleShape::new();
+		rect.set_position((x as f32, y as f32));
+		rect.set_size((1.0, 1.0));
+		rect.set_fill_color(&Color::rgb(r, g, b));
+		self.minimap.draw(&rect);
+	}
+
+	fn load_config(name: &str) -> Option<String> {
+		std::fs::read_to_string(name).ok()
+	}
+
+	fn save_config(name: &str, content: &str) {
+		if let Err(e) = std::fs::write(name, content) {
+			crate::err!("Unable to save {}: {}", name, e);
+		}
+	}
 }
 
 fn convert_key_code(key: sfml::window::Key) -> Option<ui::KeyCode> {
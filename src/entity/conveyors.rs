@@ -0,0 +1,151 @@
+use super::*;
+use crate::{
+	world::{Dir, GamePos, Mineral, TilePos, World, TILE_SIZE},
+	HashMap, HashSet,
+};
+
+/// Moves the Items that lie on Conveyors
+pub struct Conveyors {
+	/// The direction that an Item leaves its Conveyor in, once it reached the center
+	headings: HashMap<ItemID, (TilePos, Dir)>,
+	/// Junctions send their Items to their outputs in turn
+	next_output: HashMap<TilePos, usize>,
+}
+
+impl Conveyors {
+	/// The distance an Item moves per Tick
+	const SPEED: f32 = 4.0;
+	/// The distance that Items keep to the Item in front of them
+	const GAP: f32 = 7.0;
+
+	pub fn new() -> Self {
+		Self {
+			headings: HashMap::default(),
+			next_output: HashMap::default(),
+		}
+	}
+
+	/// Moves all Items on Conveyors. Returns the Minerals that were put into storage
+	pub fn update(&mut self, entities: &mut Entities, world: &mut World) -> Vec<Mineral> {
+		for item in entities.items_mut() {
+			item.prev_pos = item.pos;
+		}
+		let on_conveyors: HashSet<ItemID> = entities
+			.items()
+			.filter(|item| world.conveyor_dir(TilePos::from(item.pos)).is_some())
+			.map(|item| item.id)
+			.collect();
+		self.headings.retain(|id, _| on_conveyors.contains(id));
+		self.next_output
+			.retain(|tile, _| world.conveyor_dir(*tile).is_some());
+
+		let mut delivered = vec![];
+		for id in on_conveyors {
+			let pos = entities.item(id).pos;
+			let tile = TilePos::from(pos);
+			let center = GamePos::from(tile) + GamePos::TILE / 2.0;
+
+			let heading = match self.headings.get(&id) {
+				Some((t, dir)) if *t == tile => *dir,
+				_ if pos.dist(center) > Self::SPEED => {
+					// Items that come in from the side or were dropped go to the center first
+					let next = pos + (center - pos) / pos.dist(center) * Self::SPEED;
+					if Self::is_free(entities, id, next) {
+						entities.item_mut(id).pos = next;
+					}
+					continue;
+				}
+				_ => {
+					entities.item_mut(id).pos = center;
+					match self.next_output(tile, world) {
+						Some(dir) => {
+							self.headings.insert(id, (tile, dir));
+							dir
+						}
+						None => continue,
+					}
+				}
+			};
+
+			let pos = entities.item(id).pos;
+			let (dx, dy) = heading.as_delta();
+			let next = pos + GamePos::new(dx as f32, dy as f32) * Self::SPEED;
+			if next.dist(center) < TILE_SIZE as f32 / 2.0 {
+				if Self::is_free(entities, id, next) {
+					entities.item_mut(id).pos = next;
+				}
+				continue;
+			}
+
+			let target = match world.tile_in_dir(tile, heading) {
+				Some(target) => target,
+				None => continue,
+			};
+			if world.conveyor_dir(target).is_some() {
+				if Self::is_free(entities, id, next) {
+					entities.item_mut(id).pos = next;
+				}
+			} else if let Some(machine) = world.machine_at_mut(target) {
+				let mineral = entities.item(id).mineral;
+				if machine.accepts(mineral) {
+					machine.store(mineral);
+				} else if machine.deposit(mineral) {
+					delivered.push(mineral);
+				} else {
+					// wait until there is room
+					continue;
+				}
+				entities.remove_item(id);
+				self.headings.remove(&id);
+			} else {
+				// the output is gone, look for another one
+				self.headings.remove(&id);
+			}
+		}
+		delivered
+	}
+
+	/// Picks the direction that the next Item leaves the Conveyor at `tile` in. Items go
+	/// straight on, into a Machine in front or onto Conveyors on the sides that lead away
+	fn next_output(&mut self, tile: TilePos, world: &World) -> Option<Dir> {
+		let dir = world.conveyor_dir(tile)?;
+		let outputs: Vec<Dir> = [dir, dir.counter_clockwise(), dir.clockwise()]
+			.iter()
+			.copied()
+			.filter(|out| {
+				let target = match world.tile_in_dir(tile, *out) {
+					Some(target) => target,
+					None => return false,
+				};
+				match world.conveyor_dir(target) {
+					Some(d) if *out == dir => d != out.opposite(),
+					Some(d) => d == *out,
+					None if *out == dir => world
+						.machine_at(target)
+						.map(|m| m.entrances(world).contains(&tile))
+						.unwrap_or(false),
+					None => false,
+				}
+			})
+			.collect();
+		if outputs.is_empty() {
+			return None;
+		}
+		let next = self.next_output.entry(tile).or_insert(0);
+		let out = outputs[*next % outputs.len()];
+		*next = (*next + 1) % outputs.len();
+		Some(out)
+	}
+
+	/// Checks if the Item `id` can move to `next` without running into the Item in front of it
+	fn is_free(entities: &Entities, id: ItemID, next: GamePos) -> bool {
+		let pos = entities.item(id).pos;
+		entities
+			.items()
+			.filter(|other| other.id != id)
+			.all(|other| {
+				let dist = other.pos.dist(next);
+				dist >= Self::GAP || dist >= other.pos.dist(pos)
+			})
+	}
+}
@@ -35,7 +35,7 @@ impl Entities {
 		}
 	}
 
-	pub fn add_worker(&mut self, pos: TilePos) {
+	pub fn add_worker(&mut self, pos: TilePos) -> WorkerID {
 		let len = self.workers.len();
 		let id: WorkerID = (0..len)
 			.find(|i| !self.workers.contains_key(*i))
@@ -43,8 +43,9 @@ impl Entities {
 			.into();
 
 		self.workers.insert(id.into(), Worker::new(id, pos));
+		id
 	}
-	pub fn add_job(&mut self, variant: JobVariant) {
+	pub fn add_job(&mut self, variant: JobVariant) -> JobID {
 		let len = self.jobs.len();
 		let id: JobID = (0..len)
 			.find(|i| !self.jobs.contains_key(*i))
@@ -52,8 +53,9 @@ impl Entities {
 			.into();
 
 		self.jobs.insert(id.into(), Job::new(id, variant));
+		id
 	}
-	pub fn add_item(&mut self, pos: GamePos, mineral: Mineral) {
+	pub fn add_item(&mut self, pos: GamePos, mineral: Mineral) -> ItemID {
 		let len = self.items.len();
 		let id: ItemID = (0..len)
 			.find(|i| !self.items.contains_key(*i))
@@ -61,6 +63,7 @@ impl Entities {
 			.into();
 
 		self.items.insert(id.into(), Item::new(id, pos, mineral));
+		id
 	}
 
 	pub fn remove_worker(&mut self, id: WorkerID) {
@@ -72,6 +75,49 @@ impl Entities {
 	pub fn remove_item(&mut self, id: ItemID) {
 		self.items.remove(id.into());
 	}
+	pub fn take_item(&mut self, id: ItemID) -> Option<Item> {
+		self.items.remove(id.into())
+	}
+
+	/// Gives `job` to `worker`. Unless `append` is set, the previous plan is dropped
+	pub fn assign_job(&mut self, worker: WorkerID, job: JobID, append: bool) {
+		if !append {
+			let worker = self.worker_mut(worker);
+			worker.next_target = None;
+			let old = std::mem::take(&mut worker.plan);
+			for id in old {
+				// other Jobs return to the pool of free Jobs
+				if let Some(JobVariant::MoveTo(_)) = self.get_job(id).map(Job::variant) {
+					self.remove_job(id);
+				}
+			}
+		}
+		self.worker_mut(worker).plan.push(job);
+	}
+	/// Removes `job` from the plan of `worker` and from the list of Jobs
+	pub fn finish_job(&mut self, worker: WorkerID, job: JobID) {
+		self.worker_mut(worker).plan.retain(|id| *id != job);
+		self.remove_job(job);
+	}
+
+	/// A Job is started once it is the current Job of a Worker
+	pub fn job_started(&self, id: JobID) -> bool {
+		self.workers.values().any(|w| w.plan.first() == Some(&id))
+	}
+	/// Removes a Job that was not started yet. Returns `false` if it was already started
+	pub fn cancel_job(&mut self, id: JobID) -> bool {
+		if !self.jobs.contains_key(id.into()) {
+			return true;
+		}
+		if self.job_started(id) {
+			return false;
+		}
+		for worker in self.workers.values_mut() {
+			worker.plan.retain(|job| *job != id);
+		}
+		self.remove_job(id);
+		true
+	}
 
 	pub fn entity_at(&self, pos: GamePos) -> Option<Entity> {
 		self.items
@@ -86,6 +132,9 @@ impl Entities {
 			})
 	}
 
+	pub fn get_worker(&self, id: WorkerID) -> Option<&Worker> {
+		self.workers.get(id.into())
+	}
 	pub fn worker(&self, id: WorkerID) -> &Worker {
 		&self.workers[usize::from(id)]
 	}
@@ -105,6 +154,9 @@ impl Entities {
 		self.workers.values_mut()
 	}
 
+	pub fn get_job(&self, id: JobID) -> Option<&Job> {
+		self.jobs.get(id.into())
+	}
 	pub fn job(&self, id: JobID) -> &Job {
 		&self.jobs[usize::from(id)]
 	}
@@ -118,6 +170,9 @@ impl Entities {
 		self.jobs.values_mut()
 	}
 
+	pub fn get_item(&self, id: ItemID) -> Option<&Item> {
+		self.items.get(id.into())
+	}
 	pub fn item(&self, id: ItemID) -> &Item {
 		&self.items[usize::from(id)]
 	}
@@ -1,7 +1,7 @@
 use crate::{
 	ui::{Clickable, Hitbox},
 	world::{GamePos, Mineral},
-	Backend, BackendStyle, Colors,
+	Backend, BackendStyle, Colors, Game,
 };
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -12,6 +12,8 @@ crate::make_id!(ItemID, Item);
 pub struct Item {
 	pub id: ItemID,
 	pub pos: GamePos,
+	/// The `pos` of the last Tick, to move smoothly on Conveyors
+	pub prev_pos: GamePos,
 	pub mineral: Mineral,
 }
 
@@ -19,16 +21,23 @@ impl Item {
 	const RADIUS: f32 = 3.0;
 
 	pub fn new(id: ItemID, pos: GamePos, mineral: Mineral) -> Self {
-		Self { id, pos, mineral }
+		Self {
+			id,
+			pos,
+			prev_pos: pos,
+			mineral,
+		}
 	}
 
 	pub fn draw(&self, backend: &mut Backend) {
+		let percent = Game::time().fract();
 		backend.fill_circle(
-			self.pos,
+			self.prev_pos + (self.pos - self.prev_pos) * percent,
 			Item::RADIUS,
 			match self.mineral {
 				Mineral::Crystal => Colors::Crystal,
 				Mineral::Ore => Colors::Ore,
+				Mineral::Metal => Colors::Metal,
 			},
 		);
 	}
@@ -44,6 +53,7 @@ impl Item {
 			match self.mineral {
 				Mineral::Crystal => Colors::Crystal,
 				Mineral::Ore => Colors::Ore,
+				Mineral::Metal => Colors::Metal,
 			},
 		);
 	}
@@ -56,7 +66,4 @@ impl Clickable for Item {
 			radius: Item::RADIUS,
 		}
 	}
-	fn on_context_clicked(&mut self, item: usize) -> bool {
-		panic!("Items don't have a Context Menu, but {} was clicked", item)
-	}
 }
@@ -5,16 +5,30 @@ use crate::world::TilePos;
 pub struct JobID(usize);
 crate::make_id!(JobID, Job);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum JobVariant {
 	Destroy(TilePos),
 	MoveTo(TilePos),
 	PickUp(ItemID),
 	BringTo(ItemID, TilePos),
+	/// Waits next to the Machine at the position until the Worker has full energy
+	Recharge(TilePos),
 }
 
 use JobVariant::*;
 
+impl std::fmt::Display for JobVariant {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Destroy(pos) => write!(f, "Destroy {}", pos),
+			MoveTo(pos) => write!(f, "Move to {}", pos),
+			PickUp(item) => write!(f, "Pick up {}", item),
+			BringTo(item, pos) => write!(f, "Bring {} to {}", item, pos),
+			Recharge(pos) => write!(f, "Recharge at {}", pos),
+		}
+	}
+}
+
 #[derive(Debug)]
 pub struct Job {
 	id: JobID,
@@ -26,12 +40,21 @@ impl Job {
 		Self { id, variant }
 	}
 
-	pub fn get_target(&self, entities: &Entities) -> TilePos {
+	pub fn id(&self) -> JobID {
+		self.id
+	}
+	pub fn variant(&self) -> &JobVariant {
+		&self.variant
+	}
+
+	/// Returns `None` if the target no longer exists
+	pub fn get_target(&self, entities: &Entities) -> Option<TilePos> {
 		match self.variant {
-			Destroy(pos) => pos,
-			MoveTo(pos) => pos,
-			PickUp(item) => entities.item(item).pos.into(),
-			BringTo(_, pos) => pos,
+			Destroy(pos) => Some(pos),
+			MoveTo(pos) => Some(pos),
+			PickUp(item) => entities.get_item(item).map(|i| i.pos.into()),
+			BringTo(_, pos) => Some(pos),
+			Recharge(pos) => Some(pos),
 		}
 	}
 }
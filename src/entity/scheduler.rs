@@ -1,11 +1,21 @@
 use super::*;
-use crate::{world::World, HashSet};
+use crate::{
+	world::{GamePos, Machine, Material, Mineral, TilePos, World},
+	HashSet,
+};
 
 pub struct Scheduler {
 	known_workers: HashSet<WorkerID>,
 	known_jobs: HashSet<JobID>,
 	free_workers: HashSet<WorkerID>,
 	free_jobs: HashSet<JobID>,
+	/// Jobs that no Worker could reach since the last Tile was destroyed
+	unreachable_jobs: HashSet<JobID>,
+	/// Tired Workers that could not reach any charger since the chargers last changed
+	stranded_workers: HashSet<WorkerID>,
+	/// The number of powered chargers during the last update
+	chargers: usize,
+	delivered: Vec<Mineral>,
 }
 
 impl Scheduler {
@@ -15,10 +25,15 @@ impl Scheduler {
 			known_jobs: HashSet::default(),
 			free_workers: HashSet::default(),
 			free_jobs: HashSet::default(),
+			unreachable_jobs: HashSet::default(),
+			stranded_workers: HashSet::default(),
+			chargers: 0,
+			delivered: vec![],
 		}
 	}
 
-	pub fn update(&mut self, entities: &mut Entities, world: &mut World) {
+	/// Returns the Minerals that were put into storage during this update
+	pub fn update(&mut self, entities: &mut Entities, world: &mut World) -> Vec<Mineral> {
 		let mut marked_kill = vec![];
 		for worker in entities.workers() {
 			if worker.mark_killed {
@@ -26,14 +41,19 @@ impl Scheduler {
 			}
 		}
 		for id in marked_kill {
+			let worker = entities.worker_mut(id);
+			let pos = GamePos::from(worker.pos) + GamePos::TILE / 2.0;
+			if let Some(item) = worker.item.take() {
+				entities.add_item(pos, item.mineral);
+			}
 			entities.remove_worker(id);
 			self.known_workers.remove(&id);
-			// TODO: remove Worker
 		}
 
 		for worker in entities.workers_mut() {
 			// update between-tile movement
 			if let Some((next_pos, mut progress, total)) = worker.move_progress.take() {
+				worker.tire(Worker::WALK_DRAIN);
 				progress += 1;
 				if progress == total {
 					worker.pos = next_pos;
@@ -44,21 +64,42 @@ impl Scheduler {
 			}
 
 			if worker.move_progress.is_none() {
-				if let Some((_target, path)) = &mut worker.next_target {
-					// TODO: check path valid
-					if let Some(next) =
-						path.next()
-							.and_then(|p| if world.is_solid(p) { None } else { Some(p) })
-					{
-						let cost = world.walk_cost(worker.pos).expect("Worker on Solid Tile");
-						worker.move_progress = Some((next.into(), 0, cost));
-					} else {
-						// TODO: Reached Goal
+				let next = worker
+					.next_target
+					.as_mut()
+					.and_then(|(_target, path)| path.next())
+					.map(TilePos::from)
+					.filter(|p| !world.is_solid(*p));
+				if let Some(next) = next {
+					let mut cost = world.walk_cost(worker.pos).expect("Worker on Solid Tile");
+					if worker.is_exhausted() {
+						cost *= Worker::EXHAUSTED_SLOWDOWN;
 					}
+					worker.move_progress = Some((next, 0, cost));
+				} else {
+					// Reached Goal or Path blocked. Either way, the Job decides what's next
+					worker.next_target = None;
 				}
 			}
 		}
 
+		let chargers = world
+			.machines()
+			.filter(|m| m.machine_type.charges_workers() && m.power())
+			.count();
+		if chargers != self.chargers {
+			self.chargers = chargers;
+			self.stranded_workers.clear();
+		}
+
+		let ids: Vec<WorkerID> = entities.workers().map(|w| w.id).collect();
+		for id in ids {
+			self.check_energy(id, entities, world);
+			self.work(id, entities, world);
+		}
+
+		self.assign_free_jobs(entities, world);
+
 		let new_workers = entities
 			.workers()
 			.map(|w| w.id)
@@ -68,5 +109,287 @@ impl Scheduler {
 		for id in new_workers {
 			self.known_workers.insert(id);
 		}
+
+		std::mem::take(&mut self.delivered)
+	}
+
+	/// Puts a tired Worker's plan on hold and sends it to the closest reachable powered charger.
+	/// Without one, the Worker carries on with its plan
+	fn check_energy(&mut self, id: WorkerID, entities: &mut Entities, world: &mut World) {
+		let worker = entities.worker(id);
+		if !worker.is_tired() {
+			self.stranded_workers.remove(&id);
+			return;
+		}
+		let recharging = worker.plan.iter().any(|job| {
+			let variant = entities.get_job(*job).map(Job::variant);
+			matches!(variant, Some(JobVariant::Recharge(_)))
+		});
+		if recharging || self.stranded_workers.contains(&id) {
+			return;
+		}
+		let pos = worker.pos;
+		let mut chargers: Vec<(TilePos, Vec<TilePos>)> = world
+			.machines()
+			.filter(|m| m.machine_type.charges_workers() && m.power())
+			.map(|m| (m.pos, m.entrances(world)))
+			.collect();
+		chargers.sort_by_key(|(charger, _)| charger.dist(pos));
+		let charger = chargers.into_iter().find_map(|(charger, mut entrances)| {
+			entrances.retain(|p| world.walk_cost(*p).is_some());
+			let reachable = entrances.into_iter().any(|p| world.path(pos, p).is_some());
+			if reachable {
+				Some(charger)
+			} else {
+				None
+			}
+		});
+		match charger {
+			Some(charger) => {
+				let job = entities.add_job(JobVariant::Recharge(charger));
+				let worker = entities.worker_mut(id);
+				worker.plan.insert(0, job);
+				worker.next_target = None;
+			}
+			None => {
+				self.stranded_workers.insert(id);
+			}
+		}
+	}
+
+	/// Progresses the current Job of a Worker that is standing still
+	fn work(&mut self, id: WorkerID, entities: &mut Entities, world: &mut World) {
+		let worker = entities.worker(id);
+		if worker.move_progress.is_some() || worker.next_target.is_some() {
+			return;
+		}
+		let pos = worker.pos;
+		let job = match worker.plan.first() {
+			Some(job) => *job,
+			None => return,
+		};
+		let variant = match entities.get_job(job) {
+			Some(job) => job.variant().clone(),
+			None => {
+				entities.worker_mut(id).plan.retain(|j| *j != job);
+				return;
+			}
+		};
+
+		use JobVariant::*;
+		let finished = match variant {
+			MoveTo(target) => {
+				pos == target || !Self::walk_to(entities.worker_mut(id), world, target)
+			}
+			Destroy(target) => {
+				if world.is_empty(target) {
+					true // someone else was faster
+				} else if pos.dist(target) == 1 {
+					let worker = entities.worker_mut(id);
+					let progress = match worker.dig_progress {
+						Some((tile, progress)) if tile == target => progress + 1,
+						_ => 1,
+					};
+					worker.tire(Worker::DIG_DRAIN);
+					let hardness = world.materials()[world[target]].hardness.unwrap_or(1);
+					if progress >= hardness {
+						worker.dig_progress = None;
+						Self::destroy(target, entities, world);
+						self.unreachable_jobs.clear();
+						self.stranded_workers.clear();
+						true
+					} else {
+						worker.dig_progress = Some((target, progress));
+						false
+					}
+				} else if !Self::walk_next_to(entities.worker_mut(id), world, target) {
+					// the Wall might become reachable once others are gone
+					self.unreachable_jobs.insert(job);
+					entities.worker_mut(id).plan.retain(|j| *j != job);
+					false
+				} else {
+					false
+				}
+			}
+			PickUp(item) => match entities.get_item(item).map(|i| TilePos::from(i.pos)) {
+				Some(item_pos) if item_pos == pos => {
+					let item = entities.take_item(item);
+					let worker = entities.worker_mut(id);
+					if let Some(old) = std::mem::replace(&mut worker.item, item) {
+						let pos = GamePos::from(pos) + GamePos::TILE / 2.0;
+						entities.add_item(pos, old.mineral);
+					}
+					true
+				}
+				Some(item_pos) => !Self::walk_to(entities.worker_mut(id), world, item_pos),
+				None => true,
+			},
+			BringTo(_, target) if world.machine_at(target).is_some() => {
+				let entrances = world.machine_at(target).unwrap().entrances(world);
+				if entrances.contains(&pos) {
+					let worker = entities.worker_mut(id);
+					if let Some(item) = worker.item.take() {
+						let machine = world.machine_at_mut(target).unwrap();
+						if machine.accepts(item.mineral) {
+							machine.store(item.mineral);
+						} else if machine.deposit(item.mineral) {
+							self.delivered.push(item.mineral);
+						} else {
+							// filled up in the meantime, look for another one
+							worker.item = Some(item);
+						}
+					}
+					true
+				} else {
+					!Self::walk_to_any(entities.worker_mut(id), world, entrances)
+				}
+			}
+			Recharge(target) => match world.machine_at(target) {
+				Some(machine) if machine.power() => {
+					let entrances = machine.entrances(world);
+					if entrances.contains(&pos) {
+						let worker = entities.worker_mut(id);
+						worker.energy =
+							(worker.energy + Worker::RECHARGE_RATE).min(Worker::MAX_ENERGY);
+						worker.energy == Worker::MAX_ENERGY
+					} else {
+						!Self::walk_to_any(entities.worker_mut(id), world, entrances)
+					}
+				}
+				// the charger is gone or lost its Power
+				_ => true,
+			},
+			BringTo(_, target) => {
+				if pos == target {
+					if let Some(item) = entities.worker_mut(id).item.take() {
+						let pos = GamePos::from(pos) + GamePos::TILE / 2.0;
+						entities.add_item(pos, item.mineral);
+					}
+					true
+				} else {
+					!Self::walk_to(entities.worker_mut(id), world, target)
+				}
+			}
+		};
+		if finished {
+			entities.finish_job(id, job);
+		}
+	}
+
+	/// Sets the Worker on its way to `target`. Returns `false` if there is no Path
+	fn walk_to(worker: &mut Worker, world: &mut World, target: TilePos) -> bool {
+		worker.next_target = world.path(worker.pos, target).map(|path| (target, path));
+		worker.next_target.is_some()
+	}
+
+	/// Sets the Worker on its way to any walkable neighbor of `target`
+	fn walk_next_to(worker: &mut Worker, world: &mut World, target: TilePos) -> bool {
+		let candidates = world.neighbors_of(target).collect();
+		Self::walk_to_any(worker, world, candidates)
+	}
+
+	/// Sets the Worker on its way to the closest reachable of the walkable `candidates`
+	fn walk_to_any(worker: &mut Worker, world: &mut World, mut candidates: Vec<TilePos>) -> bool {
+		candidates.retain(|p| world.walk_cost(*p).is_some());
+		candidates.sort_by_key(|p| p.dist(worker.pos));
+		candidates
+			.into_iter()
+			.any(|p| Self::walk_to(worker, world, p))
+	}
+
+	fn destroy(target: TilePos, entities: &mut Entities, world: &mut World) {
+		let mat = world[target];
+		if !world.is_removable(target) && mat != Material::MACHINE {
+			return;
+		}
+		let mining_yield = world.materials()[mat].mining_yield;
+		world.set(target, Material::AIR);
+		if let Some((mineral, count)) = mining_yield {
+			for _ in 0..count {
+				entities.add_item(GamePos::from(target) + GamePos::TILE / 2.0, mineral);
+			}
+		}
+	}
+
+	/// Gives Jobs that nobody is working on to the closest idle Worker
+	fn assign_free_jobs(&mut self, entities: &mut Entities, world: &World) {
+		let planned: HashSet<JobID> = entities
+			.workers()
+			.flat_map(|w| w.plan.iter().copied())
+			.collect();
+
+		let mut abandoned = vec![];
+		self.free_jobs.clear();
+		for job in entities.jobs().filter(|j| !planned.contains(&j.id())) {
+			match (job.variant(), job.get_target(entities)) {
+				// only the Worker that was sent somewhere should go there
+				(JobVariant::MoveTo(_), _) | (JobVariant::Recharge(_), _) | (_, None) => {
+					abandoned.push(job.id())
+				}
+				_ if self.unreachable_jobs.contains(&job.id()) => {}
+				// nobody knows what is there yet
+				(_, Some(target)) if !world.is_explored(target) => {}
+				_ => {
+					self.free_jobs.insert(job.id());
+				}
+			}
+		}
+		for job in abandoned {
+			entities.remove_job(job);
+		}
+
+		self.free_workers = entities
+			.workers()
+			.filter(|w| w.plan.is_empty())
+			.map(|w| w.id)
+			.collect();
+
+		// idle Workers bring what they carry to the closest Machine that needs it as input,
+		// or to the closest storage with room
+		let mut haulers: Vec<(WorkerID, ItemID, TilePos)> = vec![];
+		for id in self.free_workers.iter() {
+			let worker = entities.worker(*id);
+			if let Some(item) = &worker.item {
+				let distance = |m: &&Machine| m.pos.dist(worker.pos);
+				let target = world
+					.machines()
+					.filter(|m| m.accepts(item.mineral))
+					.min_by_key(distance)
+					.or_else(|| {
+						world
+							.machines()
+							.filter(|m| m.has_room(item.mineral))
+							.min_by_key(distance)
+					})
+					.map(|m| m.pos);
+				if let Some(target) = target {
+					haulers.push((*id, item.id, target));
+				}
+			}
+		}
+		for (id, item, target) in haulers {
+			let job = entities.add_job(JobVariant::BringTo(item, target));
+			entities.worker_mut(id).plan.push(job);
+			self.free_workers.remove(&id);
+		}
+
+		let mut workers: Vec<WorkerID> = self.free_workers.iter().copied().collect();
+		workers.sort();
+		for id in workers {
+			let pos = entities.worker(id).pos;
+			let closest = self
+				.free_jobs
+				.iter()
+				.filter_map(|job| Some((*job, entities.job(*job).get_target(entities)?)))
+				.min_by_key(|(job, target)| (target.dist(pos), *job))
+				.map(|(job, _)| job);
+			if let Some(job) = closest {
+				self.free_jobs.remove(&job);
+				self.free_workers.remove(&id);
+				entities.worker_mut(id).plan.push(job);
+			}
+		}
+
+		self.known_jobs = entities.jobs().map(|j| j.id()).collect();
 	}
 }
@@ -1,8 +1,8 @@
 use super::{Item, JobID};
 use crate::{
-	ui::{Clickable, Hitbox},
+	ui::{Clickable, Hitbox, KeyCode, MenuItem},
 	world::{GamePos, Path, TilePos},
-	Backend, BackendStyle, Colors, Game,
+	Backend, BackendStyle, Colors, Command, Game,
 };
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -16,11 +16,33 @@ pub struct Worker {
 	pub plan: Vec<JobID>,
 	pub next_target: Option<(TilePos, Path)>,
 	pub move_progress: Option<(TilePos, usize, usize)>,
+	/// The Wall that is being dug out and the Ticks spent on it
+	pub dig_progress: Option<(TilePos, usize)>,
 	pub mark_killed: bool,
-	item: Option<Item>,
+	pub item: Option<Item>,
+	pub health: usize,
+	/// Drains while walking and digging. Exhausted Workers walk slower
+	pub energy: usize,
 }
 
 impl Worker {
+	/// How many Tiles far the Worker can see
+	pub const SIGHT: usize = 6;
+	pub const MAX_HEALTH: usize = 3;
+	/// How far the lamp of the Worker shines
+	pub const LIGHT_RADIUS: usize = 4;
+	pub const LIGHT_COLOR: (u8, u8, u8) = (255, 220, 170);
+	pub const MAX_ENERGY: usize = 1500;
+	/// Below this energy, the Worker interrupts its plan to recharge
+	pub const TIRED_ENERGY: usize = 300;
+	/// The energy used per Tick of walking and of digging
+	pub const WALK_DRAIN: usize = 1;
+	pub const DIG_DRAIN: usize = 3;
+	/// The energy gained per Tick next to a powered charger
+	pub const RECHARGE_RATE: usize = 30;
+	/// How many times longer exhausted Workers take for every step
+	pub const EXHAUSTED_SLOWDOWN: usize = 2;
+
 	pub fn new(id: WorkerID, pos: TilePos) -> Self {
 		Self {
 			id,
@@ -28,8 +50,29 @@ impl Worker {
 			plan: vec![],
 			next_target: None,
 			move_progress: None,
+			dig_progress: None,
 			mark_killed: false,
 			item: None,
+			health: Self::MAX_HEALTH,
+			energy: Self::MAX_ENERGY,
+		}
+	}
+
+	pub fn tire(&mut self, amount: usize) {
+		self.energy = self.energy.saturating_sub(amount);
+	}
+	pub fn is_tired(&self) -> bool {
+		self.energy < Self::TIRED_ENERGY
+	}
+	pub fn is_exhausted(&self) -> bool {
+		self.energy == 0
+	}
+
+	/// Takes `damage` from the Worker's health. The Worker dies at zero
+	pub fn injure(&mut self, damage: usize) {
+		self.health = self.health.saturating_sub(damage);
+		if self.health == 0 {
+			self.mark_killed = true;
 		}
 	}
 
@@ -39,6 +82,14 @@ impl Worker {
 		if let Some(item) = self.item.as_ref() {
 			item.draw_on_worker(backend, hitbox);
 		}
+		if self.energy < Self::MAX_ENERGY {
+			if let Hitbox::Rect { pos, size } = hitbox {
+				let pos = pos + GamePos::new(0.0, size.y);
+				let fill = self.energy as f32 / Self::MAX_ENERGY as f32;
+				backend.fill_rect(pos, (size.x, 2.0), Colors::Black);
+				backend.fill_rect(pos, (size.x * fill, 2.0), Colors::Energy);
+			}
+		}
 	}
 }
 
@@ -59,16 +110,16 @@ impl Clickable for Worker {
 		}
 	}
 
-	fn context_menu(&self) -> Box<dyn Iterator<Item = &(usize, &'static str)>> {
-		Box::new([(CONTEXT_KILL, "kill")].iter())
+	fn context_menu(&self) -> Vec<MenuItem> {
+		vec![MenuItem::new(CONTEXT_KILL, "kill").hotkey(KeyCode::Delete)]
 	}
-	fn on_context_clicked(&mut self, item: usize) -> bool {
+	fn on_context_clicked(&self, item: usize) -> Option<Command> {
 		match item {
-			CONTEXT_KILL => {
-				self.mark_killed = true;
-				true
+			CONTEXT_KILL => Some(Command::KillWorker(self.id)),
+			x => {
+				crate::err!("Invalid Context Menu Item on Worker: {}", x);
+				None
 			}
-			x => panic!("Invalid Context Menu Item on Worker: {}", x),
 		}
 	}
 }
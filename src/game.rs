@@ -1,14 +1,36 @@
 use super::{entity::*, ui::*, world::*, *};
+use rand::Rng;
+use std::collections::VecDeque;
 
 static mut TIME: f32 = 0.0;
 
+/// A change of the Mineral stock. Positive amounts are income, negative ones are spending
+#[derive(Debug, Clone, Copy)]
+pub struct Transaction {
+	pub tick: usize,
+	pub mineral: Mineral,
+	pub amount: isize,
+}
+
 pub struct Game {
 	pub mouse: Mouse,
 	pub world: World,
 	pub entities: Entities,
 	pub scheduler: Scheduler,
-	pub minerals: Vec<usize>,
+	pub conveyors: Conveyors,
+	/// All Transactions of the last minute
+	pub transactions: VecDeque<Transaction>,
 	pub menu: Menu,
+	pub history: History,
+	pub inspector: Inspector,
+	pub hud: Hud,
+	pub minimap: Minimap,
+	pub control_groups: ControlGroups,
+	pub input: InputMap,
+	pub settings: SettingsMenu,
+	pub build_tool: BuildTool,
+	pub build_palette: BuildPalette,
+	quit: bool,
 	pub update_interval: f32,
 	pub update_carry: f32,
 	pub tick: usize,
@@ -23,11 +45,30 @@ impl Game {
 
 		let mut ret = Self {
 			mouse: Mouse::new(),
-			world: World::new(size, size),
+			world: World::new(
+				size,
+				size,
+				Backend::load_config(Materials::CONFIG_FILE)
+					.map(|text| Materials::load(&text))
+					.unwrap_or_else(Materials::new),
+			),
 			entities: Entities::new(),
 			scheduler: Scheduler::new(),
-			minerals: vec![0; Mineral::count()],
+			conveyors: Conveyors::new(),
+			transactions: VecDeque::new(),
 			menu: Menu::new(),
+			history: History::new(),
+			inspector: Inspector::new(),
+			hud: Hud::new(),
+			minimap: Minimap::new(),
+			control_groups: ControlGroups::new(),
+			input: Backend::load_config(InputMap::CONFIG_FILE)
+				.map(|text| InputMap::load(&text))
+				.unwrap_or_else(InputMap::new),
+			settings: SettingsMenu::new(),
+			build_tool: BuildTool::new(),
+			build_palette: BuildPalette::new(),
+			quit: false,
 			update_interval: 0.2,
 			update_carry: 0.0,
 			tick: 0,
@@ -35,16 +76,24 @@ impl Game {
 
 		ret.world.add_machine((mid, mid), MachineType::Spawn);
 		for i in 1..4 {
-			ret.world.set((mid + i, mid), Material::Platform);
-			ret.world.set((mid + 3, mid + i), Material::Platform);
+			ret.world.set((mid + i, mid), Material::PLATFORM);
+			ret.world.set((mid + 3, mid + i), Material::PLATFORM);
 		}
 
 		ret.world.add_machine((mid + 3, mid + 3), MachineType::Lab);
 
 		ret.entities.add_worker((mid + 2, mid + 2).into());
 
+		ret.mouse.set_modifiers(ret.input.brush, ret.input.area);
+		ret.update_vision();
+		ret.update_lighting();
+
 		// TODO: <temp>
-		ret.minerals[Mineral::Crystal.num()] = 10;
+		if let Some(spawn) = ret.world.machine_at_mut((mid, mid).into()) {
+			for _ in 0..10 {
+				spawn.deposit(Mineral::Crystal);
+			}
+		}
 		ret.entities.add_item(
 			((mid - 2) as f32 * 16.0 + 5.0, (mid - 2) as f32 * 16.0 + 5.0).into(),
 			Mineral::Crystal,
@@ -64,23 +113,71 @@ impl Game {
 	}
 
 	pub fn resize(&mut self, backend: &Backend) {
-		self.mouse.set_center(
-			TilePos::new(self.world.width() / 2, self.world.height() / 2).into(),
-			GamePos::new(backend.get_width() as f32, backend.get_height() as f32),
-		);
-		self.menu.set_pos(backend.get_width() as f32);
+		let (width, height) = (backend.get_width() as f32, backend.get_height() as f32);
+		self.mouse.set_screen_size(GamePos::new(width, height));
+		self.mouse
+			.set_world_size(TilePos::new(self.world.width(), self.world.height()).into());
+		self.mouse
+			.center_on(TilePos::new(self.world.width() / 2, self.world.height() / 2).into());
+		self.menu.set_screen_size(width, height);
+		self.inspector.set_screen_size(width, height);
+		self.hud.set_screen_size(width, height);
+		self.minimap.set_screen_size(width, height);
+		self.settings.set_screen_size(width, height);
+		self.build_palette.set_screen_size(width, height);
+		if self.settings.is_open() {
+			self.settings.open(&self.input);
+		}
 		self.world.set_dirty();
 	}
 
 	pub fn draw(&mut self, backend: &mut Backend, delta_time: f32) {
+		self.mouse.update(delta_time);
+
 		self.update_carry += delta_time;
 		if self.update_carry >= self.update_interval {
 			self.tick += 1;
 			if self.tick == std::usize::MAX {
 				self.tick = 0;
 			}
-			self.world.update(self.get_mineral(Mineral::Crystal) > 0);
-			self.scheduler.update(&mut self.entities, &mut self.world);
+			for (tile, mat) in self.world.update_fluids() {
+				if mat == Material::LAVA {
+					self.burn(tile);
+				}
+			}
+			let products = self.world.update(self.get_mineral(Mineral::Crystal) > 0);
+			for (pos, product) in products {
+				self.deliver_product(pos, product);
+			}
+			let mut delivered = self.scheduler.update(&mut self.entities, &mut self.world);
+			delivered.extend(self.conveyors.update(&mut self.entities, &mut self.world));
+			for tile in self.world.update_stability() {
+				self.cave_in(tile);
+			}
+			self.update_vision();
+			self.update_lighting();
+			for mineral in delivered {
+				self.add_income(mineral, 1);
+			}
+			for (mineral, amount) in self.world.take_stock_changes() {
+				self.transactions.push_back(Transaction {
+					tick: self.tick,
+					mineral,
+					amount,
+				});
+			}
+			for (tile, mineral) in self.world.take_spilled() {
+				self.spill(tile, mineral);
+			}
+			let ticks_per_minute = (60.0 / self.update_interval) as usize;
+			while let Some(t) = self.transactions.front() {
+				if t.tick + ticks_per_minute >= self.tick {
+					break;
+				}
+				self.transactions.pop_front();
+			}
+			self.menu.validate_selection(&self.entities, &self.world);
+			self.control_groups.validate(&self.entities);
 			self.update_carry = 0.0;
 		}
 
@@ -88,28 +185,95 @@ impl Game {
 			TIME = self.tick as f32 + self.update_carry / self.update_interval;
 		}
 
+		if self.mouse.is_following() {
+			match self.menu.selection() {
+				sel @ Selection::Workers(_) => {
+					if let Some((tl, br)) = sel.bounds(&self.entities) {
+						self.mouse.follow(tl, br);
+					}
+				}
+				_ => self.mouse.set_following(false),
+			}
+		}
+
 		backend.fill(Colors::Background);
 		self.world.draw(backend);
 
 		self.entities.draw(backend);
 
+		self.build_tool
+			.draw(backend, &self.mouse, &self.entities, &self.world);
+
 		self.menu.draw(backend, &self.entities);
 
+		self.inspector.draw(
+			backend,
+			&self.mouse,
+			&self.menu,
+			&self.entities,
+			&self.world,
+		);
+
+		let minerals: Vec<_> = Mineral::all().map(|m| self.mineral_info(m)).collect();
+		let power = self.get_mineral(Mineral::Crystal) > 0;
+		self.hud.draw(backend, &minerals, &self.entities, power);
+
+		self.minimap
+			.draw(backend, &self.mouse, &self.entities, &mut self.world);
+
+		self.build_palette.update(&self.world);
+		self.build_palette.draw(backend, &self.build_tool);
+
+		self.settings.draw(backend);
+
 		self.mouse.draw(backend);
 	}
 
 	pub fn end(&mut self) {}
 
+	/// Set once the Quit Action was triggered
+	pub fn should_quit(&self) -> bool {
+		self.quit
+	}
+
 	pub fn on_mouse_event(&mut self, event: MouseEvent) {
 		use SelectionInfo::*;
+		if self.settings.is_open() {
+			if let Click(pos) = self.mouse.on_event(event) {
+				let screen_pos = self.mouse.world_to_screen(pos);
+				self.settings.process_click(screen_pos, &mut self.input);
+				self.mouse.set_modifiers(self.input.brush, self.input.area);
+			}
+			return;
+		}
+		if self.minimap.process_event(event, &mut self.mouse)
+			|| self
+				.build_palette
+				.process_event(event, &mut self.mouse, &mut self.build_tool)
+			|| self.build_tool.process_event(
+				event,
+				&mut self.mouse,
+				&mut self.entities,
+				&mut self.world,
+				&mut self.history,
+			) {
+			return;
+		}
 		let new_selection = match self.mouse.on_event(event) {
 			Click(pos) => {
 				let w_pos: TilePos = pos.into();
-				if self.menu.process_click(
-					self.mouse.world_to_screen(pos),
-					&mut self.entities,
-					&mut self.world,
-				) {
+				let screen_pos = self.mouse.world_to_screen(pos);
+				if let Some(id) = self.hud.process_click(screen_pos, &self.entities) {
+					let worker = self.entities.worker(id);
+					self.mouse.center_on(worker.hitbox().center());
+					Some(Selection::Workers(std::iter::once(id).collect()))
+				} else if self.hud.contains(screen_pos)
+					|| self.menu.process_click(
+						screen_pos,
+						&mut self.entities,
+						&mut self.world,
+						&mut self.history,
+					) {
 					None
 				} else if let Some(entity) = self.entities.entity_at(pos) {
 					Some(match entity {
@@ -118,15 +282,7 @@ impl Game {
 					})
 				} else if self.world.machine_at(w_pos).is_some() {
 					Some(Selection::Machine(w_pos))
-				} else if self.world.is_visible(w_pos) {
-					// TODO: <temp>
-					let mut w = self
-						.entities
-						.workers_mut()
-						.next()
-						.expect("You killed my Worker :/");
-					w.next_target = self.world.path(w.pos, w_pos).map(|path| (w_pos, path));
-					// </temp>
+				} else if self.world.is_explored(w_pos) {
 					Some(Selection::Air(w_pos))
 				} else {
 					Some(Selection::Nothing)
@@ -146,7 +302,7 @@ impl Game {
 							size: GamePos::TILE,
 						})
 					})
-					.filter(|&tile| world.is_visible(tile) && world.is_solid(tile));
+					.filter(|&tile| world.is_explored(tile) && world.is_solid(tile));
 
 				let mut selection = if append {
 					match self.menu.take_selection() {
@@ -186,24 +342,263 @@ impl Game {
 					Selection::Workers(selection)
 				})
 			}
+			Order(pos, append) => {
+				self.order(pos, append);
+				None
+			}
 			NoChange => None,
 		};
 		if let Some(selection) = new_selection {
+			self.menu.open_at(self.mouse.pos());
 			self.menu
 				.set_selection(selection, &self.entities, &self.world);
 		}
 	}
 
+	/// Gives a right click order to the selected Workers
+	fn order(&mut self, pos: GamePos, append: bool) {
+		let entities = &mut self.entities;
+		// the selection can still hold Workers that died since
+		let mut workers: Vec<WorkerID> = match self.menu.selection() {
+			Selection::Workers(workers) => workers
+				.iter()
+				.copied()
+				.filter(|id| entities.get_worker(*id).is_some())
+				.collect(),
+			_ => return,
+		};
+		let tile: TilePos = pos.into();
+		if workers.is_empty() || !self.world.is_explored(tile) {
+			return;
+		}
+		workers.sort_by_key(|id| entities.worker(*id).pos.dist(tile));
+
+		if let Some(Entity::Item(item)) = entities.entity_at(pos) {
+			let job = entities.add_job(JobVariant::PickUp(item));
+			entities.assign_job(workers[0], job, append);
+		} else if self.world.materials()[self.world[tile]].is_minable() {
+			let job = entities.add_job(JobVariant::Destroy(tile));
+			entities.assign_job(workers[0], job, append);
+		} else if self.world.walk_cost(tile).is_some() {
+			let targets = self.world.walkable_around(tile, workers.len());
+			for (id, target) in workers.into_iter().zip(targets) {
+				let job = entities.add_job(JobVariant::MoveTo(target));
+				entities.assign_job(id, job, append);
+			}
+		}
+	}
+
 	pub fn on_key_press(&mut self, code: Option<KeyCode>, shift: ButtonState, ctrl: ButtonState) {
 		self.mouse.set_shift(shift);
 		self.mouse.set_ctrl(ctrl);
+		let (shift, ctrl): (bool, bool) = (shift.into(), ctrl.into());
 
-		if code == Some(KeyCode::Letter('h')) {
-			self.world.toggle_debug_mode();
+		if self
+			.settings
+			.process_key(code, shift, ctrl, &mut self.input)
+		{
+			self.mouse.set_modifiers(self.input.brush, self.input.area);
+			return;
+		}
+
+		let code = match code {
+			Some(code) => code,
+			None => return,
+		};
+		if !ctrl
+			&& self
+				.menu
+				.process_key(code, &mut self.entities, &mut self.world, &mut self.history)
+		{
+			return;
+		}
+		match self.input.action(code.normalized(), shift, ctrl) {
+			Some(action) => self.perform(action),
+			// Control Groups are always on the number keys
+			None => match code {
+				KeyCode::Number(n) if ctrl => {
+					if let Selection::Workers(workers) = self.menu.selection() {
+						self.control_groups.store(n, workers.clone());
+					}
+				}
+				KeyCode::Number(n) => self.recall_control_group(n),
+				_ => {}
+			},
 		}
 	}
 
+	pub fn perform(&mut self, action: Action) {
+		match action {
+			Action::Undo => self.undo(),
+			Action::Redo => self.redo(),
+			Action::PanUp => self.mouse.pan_in_dir(Dir::Up),
+			Action::PanDown => self.mouse.pan_in_dir(Dir::Down),
+			Action::PanLeft => self.mouse.pan_in_dir(Dir::Left),
+			Action::PanRight => self.mouse.pan_in_dir(Dir::Right),
+			Action::CenterOnSelection => self.center_on_selection(),
+			Action::Follow => {
+				let following = matches!(self.menu.selection(), Selection::Workers(_));
+				self.mouse
+					.set_following(following && !self.mouse.is_following());
+			}
+			Action::ToggleEdgeScrolling => self.mouse.edge_scrolling = !self.mouse.edge_scrolling,
+			Action::ToggleDebug => self.world.toggle_debug_mode(),
+			Action::ToggleEditor => self.world.toggle_editor_mode(),
+			Action::BuildPlatforms => {
+				if self.build_tool.is_active() {
+					self.build_tool.cancel();
+				} else {
+					self.build_tool.start(MachineType::Platform);
+				}
+			}
+			Action::Rotate => self.build_tool.rotate(),
+			Action::Settings => self.settings.open(&self.input),
+			Action::Quit => self.quit = true,
+		}
+	}
+
+	/// Selects the Workers in a Control Group. Recalling it twice in a row centers the camera on it
+	pub fn recall_control_group(&mut self, group: usize) {
+		let time = Game::time() * self.update_interval;
+		if let Some((workers, double_tap)) = self.control_groups.recall(group, time) {
+			self.menu.open_at(self.mouse.pos());
+			self.menu
+				.set_selection(Selection::Workers(workers), &self.entities, &self.world);
+			if double_tap {
+				self.center_on_selection();
+			}
+		}
+	}
+
+	pub fn center_on_selection(&mut self) {
+		if let Some((tl, br)) = self.menu.selection().bounds(&self.entities) {
+			self.mouse.center_on((tl + br) / 2.0);
+		}
+	}
+
+	/// Injures the Workers that are caught under the collapsing Tile
+	fn cave_in(&mut self, tile: TilePos) {
+		let mut rng = rand::thread_rng();
+		for worker in self.entities.workers_mut().filter(|w| w.pos == tile) {
+			worker.injure(rng.gen_range(1, Worker::MAX_HEALTH + 1));
+		}
+	}
+
+	/// Kills the Workers that are caught in Lava
+	fn burn(&mut self, tile: TilePos) {
+		for worker in self.entities.workers_mut().filter(|w| w.pos == tile) {
+			worker.injure(Worker::MAX_HEALTH);
+		}
+	}
+
+	fn update_vision(&mut self) {
+		let eyes = self
+			.entities
+			.workers()
+			.map(|worker| (worker.pos, Worker::SIGHT))
+			.collect();
+		self.world.update_vision(eyes);
+	}
+
+	fn update_lighting(&mut self) {
+		let lights = self
+			.entities
+			.workers()
+			.map(|worker| (worker.pos, Worker::LIGHT_RADIUS, Worker::LIGHT_COLOR))
+			.collect();
+		self.world.update_lighting(lights);
+	}
+
+	pub fn undo(&mut self) {
+		if self.history.undo(&mut self.entities, &mut self.world) {
+			// the Selection might contain something that no longer exists
+			self.menu
+				.set_selection(Selection::Nothing, &self.entities, &self.world);
+		}
+	}
+	pub fn redo(&mut self) {
+		if self.history.redo(&mut self.entities, &mut self.world) {
+			self.menu
+				.set_selection(Selection::Nothing, &self.entities, &self.world);
+		}
+	}
+
+	/// Puts what a Machine at `pos` produced into the World. It comes out of an Output Port if
+	/// possible
+	fn deliver_product(&mut self, pos: TilePos, product: Product) {
+		let machine = match self.world.machine_at(pos) {
+			Some(machine) => machine,
+			None => return,
+		};
+		let outputs = machine
+			.ports()
+			.into_iter()
+			.filter(|(_, _, kind)| *kind == PortKind::Output)
+			.filter_map(|(tile, side, _)| self.world.tile_in_dir(tile, side));
+		let mut candidates: Vec<TilePos> = outputs.collect();
+		candidates.extend(machine.border(&self.world));
+		let free = match candidates
+			.into_iter()
+			.find(|p| self.world.walk_cost(*p).is_some())
+		{
+			Some(free) => free,
+			None => {
+				crate::err!("No space for the {:?} of the Machine at {}", product, pos);
+				return;
+			}
+		};
+		match product {
+			Product::Worker => {
+				self.entities.add_worker(free);
+			}
+			Product::Mineral(mineral) => {
+				let pos = GamePos::from(free) + GamePos::TILE / 2.0;
+				let item = self.entities.add_item(pos, mineral);
+				// Workers carry it away unless a Conveyor does
+				if self.world.conveyor_dir(free).is_none() {
+					self.entities.add_job(JobVariant::PickUp(item));
+				}
+			}
+		}
+	}
+
+	/// Drops a Mineral from a removed storage near `tile` for the Workers to pick up
+	fn spill(&mut self, tile: TilePos, mineral: Mineral) {
+		let free = std::iter::once(tile)
+			.chain(self.world.neighbors_of(tile))
+			.find(|p| self.world.walk_cost(*p).is_some())
+			.unwrap_or(tile);
+		let pos = GamePos::from(free) + GamePos::TILE / 2.0;
+		let item = self.entities.add_item(pos, mineral);
+		self.entities.add_job(JobVariant::PickUp(item));
+	}
+
+	/// The amount of `mineral` in all storages
 	pub fn get_mineral(&self, mineral: Mineral) -> usize {
-		self.minerals[mineral.num()]
+		self.world.stock(mineral)
+	}
+	/// Records that `amount` of `mineral` was put into storage
+	pub fn add_income(&mut self, mineral: Mineral, amount: usize) {
+		self.transactions.push_back(Transaction {
+			tick: self.tick,
+			mineral,
+			amount: amount as isize,
+		});
+	}
+	pub fn mineral_info(&self, mineral: Mineral) -> MineralInfo {
+		let mut info = MineralInfo {
+			mineral,
+			count: self.get_mineral(mineral),
+			income: 0,
+			spent: 0,
+		};
+		for t in self.transactions.iter().filter(|t| t.mineral == mineral) {
+			if t.amount > 0 {
+				info.income += t.amount as usize;
+			} else {
+				info.spent += (-t.amount) as usize;
+			}
+		}
+		info
 	}
 }
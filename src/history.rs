@@ -0,0 +1,292 @@
+use super::{entity::*, world::*};
+
+/// A Player action that can be undone
+#[derive(Debug, Clone)]
+pub enum Command {
+	RemoveTiles(Vec<TilePos>),
+	PlacePlatform(TilePos),
+	/// Places a Machine with its top left corner at the TilePos
+	PlaceMachine(TilePos, MachineType, Dir),
+	DestroyMachine(TilePos),
+	StartProduction(TilePos),
+	KillWorker(WorkerID),
+	Batch(Vec<Command>),
+}
+
+#[derive(Debug, Clone)]
+struct TileState {
+	pos: TilePos,
+	tile: Tile,
+	/// The Machine covering the Tile with its `pos` and rotation
+	machine: Option<(TilePos, MachineType, Dir)>,
+}
+
+impl TileState {
+	fn save(world: &World, pos: TilePos) -> Self {
+		let tile = world.tile(pos).expect("Invalid TilePos");
+		let machine = if tile.structure == Some(Material::MACHINE) {
+			world
+				.machine_at(pos)
+				.map(|m| (m.pos, m.machine_type.clone(), m.rotation))
+		} else {
+			None
+		};
+		Self { pos, tile, machine }
+	}
+	fn restore(mut self, world: &mut World) {
+		if self.machine.is_some() {
+			// add_machine puts the Machine back on the structure Layer
+			self.tile.structure = None;
+		}
+		world.set_tile(self.pos, self.tile);
+		if let Some((pos, machine, rotation)) = self.machine {
+			world.add_rotated_machine(pos, machine, rotation);
+		}
+	}
+}
+
+/// What is needed to take back an executed Command
+#[derive(Debug)]
+enum Revert {
+	Tiles(Vec<TileState>),
+	ConstructionSite(TilePos),
+	/// The Jobs with their variant, since a finished Job's ID is handed out again
+	Jobs(Vec<(JobID, JobVariant)>),
+	Respawn(TilePos),
+	Production(TilePos),
+}
+
+impl Command {
+	fn execute(&self, entities: &mut Entities, world: &mut World) -> Option<Revert> {
+		use Command::*;
+		let editor = world.editor_mode();
+		match self {
+			RemoveTiles(tiles) if editor => {
+				let old = tiles.iter().map(|p| TileState::save(world, *p)).collect();
+				for tile in tiles {
+					world.set(*tile, Material::AIR);
+				}
+				Some(Revert::Tiles(old))
+			}
+			RemoveTiles(tiles) => {
+				let jobs = tiles
+					.iter()
+					.map(|p| {
+						let variant = JobVariant::Destroy(*p);
+						(entities.add_job(variant.clone()), variant)
+					})
+					.collect();
+				Some(Revert::Jobs(jobs))
+			}
+			PlacePlatform(pos) if editor => {
+				let old = TileState::save(world, *pos);
+				world.set(*pos, Material::PLATFORM);
+				Some(Revert::Tiles(vec![old]))
+			}
+			PlacePlatform(pos) => {
+				let (mineral, cost) = MachineType::Platform.cost();
+				if !world.spend_mineral(mineral, cost) {
+					return None;
+				}
+				world.add_machine(
+					*pos,
+					MachineType::ConstructionSite(Box::new(MachineType::Platform)),
+				);
+				Some(Revert::ConstructionSite(*pos))
+			}
+			PlaceMachine(pos, machine, rotation) if editor => {
+				let (width, height) = machine.footprint(*rotation);
+				let old = pos
+					.rect_iter(*pos + TilePos::new(width - 1, height - 1))
+					.map(|p| TileState::save(world, p))
+					.collect();
+				world.add_rotated_machine(*pos, machine.clone(), *rotation);
+				Some(Revert::Tiles(old))
+			}
+			PlaceMachine(pos, machine, rotation) => {
+				let (mineral, cost) = machine.cost();
+				if !world.spend_mineral(mineral, cost) {
+					return None;
+				}
+				world.add_rotated_machine(
+					*pos,
+					MachineType::ConstructionSite(Box::new(machine.clone())),
+					*rotation,
+				);
+				Some(Revert::ConstructionSite(*pos))
+			}
+			DestroyMachine(pos) if editor => {
+				let old = TileState::save(world, *pos);
+				world.set(*pos, Material::AIR);
+				Some(Revert::Tiles(vec![old]))
+			}
+			DestroyMachine(pos) => {
+				let variant = JobVariant::Destroy(*pos);
+				let job = entities.add_job(variant.clone());
+				Some(Revert::Jobs(vec![(job, variant)]))
+			}
+			StartProduction(pos) => {
+				let started = world
+					.machine_at_mut(*pos)
+					.map(|m| m.start_cycle())
+					.unwrap_or(false);
+				if started {
+					Some(Revert::Production(*pos))
+				} else {
+					None
+				}
+			}
+			KillWorker(id) => {
+				let worker = entities.worker_mut(*id);
+				worker.mark_killed = true;
+				// a dead Worker stays dead during Gameplay
+				if editor {
+					Some(Revert::Respawn(worker.pos))
+				} else {
+					None
+				}
+			}
+			Batch(_) => panic!("Batch Commands are executed by the History"),
+		}
+	}
+}
+
+impl Revert {
+	/// Reverts the changes of `command` and returns the Command that would redo them
+	fn apply(self, command: Command, entities: &mut Entities, world: &mut World) -> Command {
+		match self {
+			Revert::Tiles(tiles) => {
+				for tile in tiles.into_iter().rev() {
+					tile.restore(world);
+				}
+			}
+			Revert::ConstructionSite(pos) => {
+				let cost = world.machine_at(pos).and_then(|m| match &m.machine_type {
+					MachineType::ConstructionSite(machine) => Some(machine.cost()),
+					_ => None,
+				});
+				// a finished Machine stays and keeps its cost
+				if let Some((mineral, cost)) = cost {
+					world.set(pos, Material::AIR);
+					world.refund_mineral(pos, mineral, cost);
+				}
+			}
+			Revert::Jobs(jobs) => {
+				for (job, variant) in jobs {
+					// the ID might belong to a newer Job if this one is already done
+					if entities.get_job(job).map(Job::variant) != Some(&variant) {
+						continue;
+					}
+					// Jobs that a Worker already started are carried out regardless
+					entities.cancel_job(job);
+				}
+			}
+			Revert::Respawn(pos) => {
+				// the Worker might get a new ID, which the redo has to target
+				return Command::KillWorker(entities.add_worker(pos));
+			}
+			Revert::Production(pos) => {
+				// a finished cycle can't be taken back
+				if let Some(machine) = world.machine_at_mut(pos) {
+					machine.cancel_cycle();
+				}
+			}
+		}
+		command
+	}
+}
+
+/// Undo and Redo stacks of the Commands executed by the Player
+#[derive(Debug)]
+pub struct History {
+	undo: Vec<Vec<(Command, Revert)>>,
+	redo: Vec<Command>,
+}
+
+impl History {
+	const MAX_LEN: usize = 100;
+
+	pub fn new() -> Self {
+		Self {
+			undo: vec![],
+			redo: vec![],
+		}
+	}
+
+	pub fn execute(&mut self, command: Command, entities: &mut Entities, world: &mut World) {
+		self.redo.clear();
+		self.push(command, entities, world);
+	}
+
+	fn push(&mut self, command: Command, entities: &mut Entities, world: &mut World) {
+		let mut step = vec![];
+		let mut todo = vec![command];
+		while let Some(command) = todo.pop() {
+			if let Command::Batch(commands) = command {
+				todo.extend(commands.into_iter().rev());
+			} else if let Some(revert) = command.execute(entities, world) {
+				step.push((command, revert));
+			}
+		}
+		if step.is_empty() {
+			return;
+		}
+		self.undo.push(step);
+		if self.undo.len() > Self::MAX_LEN {
+			self.undo.remove(0);
+		}
+	}
+
+	/// Reverts the last Command. Returns `false` if there was nothing to undo
+	pub fn undo(&mut self, entities: &mut Entities, world: &mut World) -> bool {
+		let step = match self.undo.pop() {
+			Some(step) => step,
+			None => return false,
+		};
+		let mut commands: Vec<Command> = step
+			.into_iter()
+			.rev()
+			.map(|(command, revert)| revert.apply(command, entities, world))
+			.collect();
+		commands.reverse();
+		self.redo.push(Command::Batch(commands));
+		true
+	}
+
+	/// Executes the last undone Command again. Returns `false` if there was nothing to redo
+	pub fn redo(&mut self, entities: &mut Entities, world: &mut World) -> bool {
+		match self.redo.pop() {
+			Some(command) => {
+				self.push(command, entities, world);
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn undo_after_job_id_reuse() {
+		let mut world = World::new(16, 16, Materials::new());
+		let mut entities = Entities::new();
+		let mut history = History::new();
+
+		let pos = TilePos::new(3, 3);
+		history.execute(Command::RemoveTiles(vec![pos]), &mut entities, &mut world);
+		let old = entities.jobs().next().unwrap().id();
+
+		// the Job is done and its ID goes to an unrelated Job
+		entities.remove_job(old);
+		let other = TilePos::new(5, 5);
+		let new = entities.add_job(JobVariant::Destroy(other));
+		assert_eq!(new, old);
+
+		assert!(history.undo(&mut entities, &mut world));
+		let job = entities.get_job(new).expect("unrelated Job was cancelled");
+		assert_eq!(job.variant(), &JobVariant::Destroy(other));
+	}
+}
@@ -10,15 +10,20 @@ pub use backend::{Backend, BackendStyle, Colors};
 mod game;
 pub use game::*;
 
+mod history;
+pub use history::*;
+
 pub mod ui;
 
 pub mod entity {
+	mod conveyors;
 	mod entities;
 	mod exec;
 	mod item;
 	mod job;
 	mod scheduler;
 	mod worker;
+	pub use conveyors::*;
 	pub use entities::*;
 	pub use exec::*;
 	pub use item::*;
@@ -29,16 +34,24 @@ pub mod entity {
 
 pub mod world {
 	mod dir;
+	mod fluids;
 	mod grid;
+	mod lighting;
 	mod machine;
+	mod map_image;
 	mod material;
 	mod pos;
+	mod stability;
 	mod wrapper;
 	pub use dir::*;
+	pub use fluids::*;
 	pub use grid::*;
+	pub use lighting::*;
 	pub use machine::*;
+	pub use map_image::*;
 	pub use material::*;
 	pub use pos::*;
+	pub use stability::*;
 	pub use wrapper::*;
 }
 
@@ -0,0 +1,147 @@
+use super::{BuildTool, Button, ButtonState, Clickable, Mouse, MouseButton, MouseEvent};
+use crate::{
+	backend::{CHAR_WIDTH, TEXT_SIZE},
+	world::{GamePos, MachineType, World, TILE_SIZE},
+	Backend, BackendStyle, Colors,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteEntry {
+	Build(MachineType),
+	ShowPower,
+}
+
+/// A row of Buttons at the bottom of the screen to choose what to build
+#[derive(Debug)]
+pub struct BuildPalette {
+	buttons: Vec<Button<PaletteEntry>>,
+	screen_size: GamePos,
+	show_power: bool,
+}
+
+impl BuildPalette {
+	pub fn new() -> Self {
+		Self {
+			buttons: vec![],
+			screen_size: GamePos::new(0.0, 0.0),
+			show_power: false,
+		}
+	}
+
+	pub fn set_screen_size(&mut self, width: f32, height: f32) {
+		self.screen_size = GamePos::new(width, height);
+		self.layout();
+	}
+
+	fn layout(&mut self) {
+		let height = TILE_SIZE.max(TEXT_SIZE) as f32 + 2.0;
+		let button = |entry, text: String, icon: Option<(usize, usize)>| {
+			let icon_width = if icon.is_some() {
+				TILE_SIZE as f32 + 2.0
+			} else {
+				0.0
+			};
+			let width = text.chars().count() as f32 * CHAR_WIDTH + icon_width + 4.0;
+			let mut button = Button::new(
+				entry,
+				text,
+				GamePos::new(0.0, 0.0),
+				GamePos::new(width, height),
+			);
+			button.icon = icon;
+			button
+		};
+
+		self.buttons = MachineType::all_buildable()
+			.into_iter()
+			.map(|machine| {
+				let (mineral, cost) = machine.cost();
+				let text = format!("{:?} ({} {:?})", machine, cost, mineral);
+				let icon = machine.icon();
+				button(PaletteEntry::Build(machine), text, Some(icon))
+			})
+			.collect();
+		let power = if self.show_power {
+			"[x] Power"
+		} else {
+			"[ ] Power"
+		};
+		self.buttons
+			.push(button(PaletteEntry::ShowPower, power.to_string(), None));
+
+		let total = self.buttons.iter().map(|b| b.size.x + 4.0).sum::<f32>() - 4.0;
+		let mut x = (self.screen_size.x - total) / 2.0;
+		let y = self.screen_size.y - height - 5.0;
+		for button in self.buttons.iter_mut() {
+			button.pos = GamePos::new(x, y);
+			x += button.size.x + 4.0;
+		}
+	}
+
+	/// Disables the Machines that the Player can't afford. Everything is free in editor mode
+	pub fn update(&mut self, world: &World) {
+		for button in self.buttons.iter_mut() {
+			if let PaletteEntry::Build(machine) = &button.identifier {
+				let (mineral, cost) = machine.cost();
+				button.enabled = world.editor_mode() || world.stock(mineral) >= cost;
+			}
+		}
+	}
+
+	pub fn draw(&self, backend: &mut Backend, build_tool: &BuildTool) {
+		for button in self.buttons.iter() {
+			button.draw(backend);
+			let selected = match &button.identifier {
+				PaletteEntry::Build(machine) => build_tool.building() == Some(machine),
+				PaletteEntry::ShowPower => false,
+			};
+			if selected {
+				backend.absolute_mode(true);
+				backend.stroke_rect(button.pos, button.size, 2.0, Colors::Highlight);
+				backend.absolute_mode(false);
+			}
+		}
+	}
+
+	pub fn contains(&self, pos: GamePos) -> bool {
+		self.buttons.iter().any(|b| b.contains(pos))
+	}
+
+	/// Chooses what the BuildTool builds. Returns `true` if the event was consumed
+	pub fn process_event(
+		&mut self,
+		event: MouseEvent,
+		mouse: &mut Mouse,
+		build_tool: &mut BuildTool,
+	) -> bool {
+		let pos = mouse.pos();
+		match event {
+			MouseEvent::ClickDown(MouseButton::Left) => self.contains(pos),
+			MouseEvent::ClickUp(MouseButton::Left) => {
+				let entry = match self.buttons.iter().find(|b| b.contains(pos)) {
+					Some(button) if button.enabled => button.identifier.clone(),
+					Some(_) => return true,
+					None => return false,
+				};
+				// the ClickDown might have happened outside of the Palette
+				mouse.set_button(MouseButton::Left, ButtonState::Up);
+				match entry {
+					PaletteEntry::Build(machine) => {
+						if build_tool.building() == Some(&machine) {
+							build_tool.cancel();
+						} else {
+							build_tool.start(machine);
+						}
+					}
+					PaletteEntry::ShowPower => {
+						self.show_power = !self.show_power;
+						build_tool.show_power = self.show_power;
+						self.layout();
+					}
+				}
+				true
+			}
+			_ => false,
+		}
+	}
+}
@@ -0,0 +1,308 @@
+use super::{Mouse, MouseButton, MouseEvent};
+use crate::{
+	backend::{CHAR_WIDTH, LINE_HEIGHT},
+	entity::Entities,
+	world::{conveyor_sprite, Dir, GamePos, Machine, MachineType, Material, TilePos, World},
+	Backend, BackendStyle, Colors, Command, HashSet, History,
+};
+
+/// Places Machines under the Mouse. Platforms and Conveyors are placed in lines or rectangles by
+/// dragging
+#[derive(Debug)]
+pub struct BuildTool {
+	building: Option<MachineType>,
+	/// The Tile where the drag started
+	start: Option<TilePos>,
+	/// The direction that the top of the Machine faces
+	rotation: Dir,
+	/// Highlight the Tiles that have Power nearby
+	pub show_power: bool,
+}
+
+impl BuildTool {
+	pub fn new() -> Self {
+		Self {
+			building: None,
+			start: None,
+			rotation: Dir::Up,
+			show_power: false,
+		}
+	}
+
+	pub fn building(&self) -> Option<&MachineType> {
+		self.building.as_ref()
+	}
+
+	pub fn is_active(&self) -> bool {
+		self.building.is_some()
+	}
+	pub fn start(&mut self, machine: MachineType) {
+		self.building = Some(machine);
+		self.start = None;
+	}
+	pub fn cancel(&mut self) {
+		self.building = None;
+		self.start = None;
+	}
+	pub fn rotate(&mut self) {
+		self.rotation = self.rotation.clockwise();
+	}
+
+	/// The Tile under the Mouse, clamped to the World
+	fn hovered(mouse: &Mouse, world: &World) -> TilePos {
+		let pos = mouse.pos_world();
+		let end = TilePos::from(GamePos::new(pos.x.max(0.0), pos.y.max(0.0)));
+		TilePos::new(end.x.min(world.width() - 1), end.y.min(world.height() - 1))
+	}
+
+	/// The Tiles that would be built if the Mouse was released now
+	fn tiles(&self, mouse: &Mouse, world: &World) -> Vec<TilePos> {
+		let end = Self::hovered(mouse, world);
+		let start = match self.start {
+			Some(start) if self.building.as_ref().map(MachineType::is_dragged) == Some(true) => {
+				start
+			}
+			_ => return vec![end],
+		};
+		if mouse.area_mode() {
+			return start.rect_iter(end).collect();
+		}
+		let dx = (start.x as isize - end.x as isize).abs();
+		let dy = (start.y as isize - end.y as isize).abs();
+		if dx >= dy {
+			start.rect_iter(TilePos::new(end.x, start.y)).collect()
+		} else {
+			start.rect_iter(TilePos::new(start.x, end.y)).collect()
+		}
+	}
+
+	/// The direction of the Machines that would be built. Conveyors face the way they are dragged
+	fn rotation(&self, mouse: &Mouse, world: &World) -> Dir {
+		let end = Self::hovered(mouse, world);
+		let start = match self.start {
+			Some(start) if self.building == Some(MachineType::Conveyor) && start != end => start,
+			_ => return self.rotation,
+		};
+		let dx = end.x as isize - start.x as isize;
+		let dy = end.y as isize - start.y as isize;
+		if dx.abs() >= dy.abs() {
+			if dx > 0 {
+				Dir::Right
+			} else {
+				Dir::Left
+			}
+		} else if dy > 0 {
+			Dir::Down
+		} else {
+			Dir::Up
+		}
+	}
+
+	/// The Tiles that `machine` would cover with its top left corner at `tile`
+	fn footprint(&self, machine: &MachineType, tile: TilePos) -> Vec<TilePos> {
+		let (width, height) = machine.footprint(self.rotation);
+		tile.rect_iter(tile + TilePos::new(width - 1, height - 1))
+			.collect()
+	}
+
+	/// Returns why `machine` can't be built with its top left corner at `tile`
+	fn invalid_reason(
+		&self,
+		machine: &MachineType,
+		tile: TilePos,
+		entities: &Entities,
+		world: &World,
+	) -> Option<&'static str> {
+		let footprint = self.footprint(machine, tile);
+		let (width, height) = machine.footprint(self.rotation);
+		if tile.x + width > world.width() || tile.y + height > world.height() {
+			return Some("outside of the World");
+		}
+		let (mineral, cost) = machine.cost();
+		if !world.editor_mode() && world.stock(mineral) < cost {
+			return Some("too expensive");
+		}
+		if let Some(reason) = footprint
+			.iter()
+			.find_map(|t| Self::tile_invalid_reason(machine, *t, entities, world))
+		{
+			Some(reason)
+		} else if machine.needs_power() && !footprint.iter().any(|t| world.is_powered_nearby(*t)) {
+			Some("no Power nearby")
+		} else {
+			None
+		}
+	}
+
+	/// Returns why a single Tile of `machine` can't be built on `tile`
+	fn tile_invalid_reason(
+		machine: &MachineType,
+		tile: TilePos,
+		entities: &Entities,
+		world: &World,
+	) -> Option<&'static str> {
+		if !world.is_explored(tile) {
+			Some("not explored")
+		} else if !world.is_empty(tile) {
+			// undoing the construction would lose whatever was there
+			Some("not empty")
+		} else if machine.is_dragged() {
+			None
+		} else if world.walk_cost(tile).is_none() {
+			Some("not walkable")
+		} else if entities.workers().any(|w| w.pos == tile) {
+			Some("Worker in the way")
+		} else {
+			None
+		}
+	}
+
+	pub fn draw(&self, backend: &mut Backend, mouse: &Mouse, entities: &Entities, world: &World) {
+		let machine = match &self.building {
+			Some(machine) => machine,
+			None => return,
+		};
+
+		if self.show_power {
+			let covered: HashSet<TilePos> = world
+				.machines()
+				.filter(|m| m.has_power_source())
+				.flat_map(|m| world.neighbors_of(m.pos))
+				.filter(|tile| world.is_explored(*tile) && world.walk_cost(*tile).is_some())
+				.collect();
+			for tile in covered {
+				backend.fill_rect(tile, GamePos::TILE, Colors::PowerCoverage);
+			}
+		}
+
+		if !machine.is_dragged() {
+			let tile = self.tiles(mouse, world)[0];
+			let reason = self.invalid_reason(machine, tile, entities, world);
+			let tint = if reason.is_none() {
+				Colors::Ghost
+			} else {
+				Colors::Invalid
+			};
+			for t in self.footprint(machine, tile) {
+				backend.draw_asset(machine.icon(), t);
+				backend.fill_rect(t, GamePos::TILE, tint);
+			}
+			let ghost = Machine::new(tile, machine.clone(), self.rotation);
+			ghost.draw_ports(backend);
+
+			let (mineral, cost) = machine.cost();
+			let mut text = format!("{:?}: {} {:?}", machine, cost, mineral);
+			if let Some(reason) = reason {
+				text.push_str(&format!(" ({})", reason));
+			}
+			draw_label(backend, &text, mouse.pos());
+			return;
+		}
+
+		let tiles = self.tiles(mouse, world);
+		let ghosts: HashSet<TilePos> = tiles
+			.iter()
+			.copied()
+			.filter(|tile| {
+				self.invalid_reason(machine, *tile, entities, world)
+					.is_none()
+			})
+			.collect();
+
+		// existing Platforms connect to the new ones
+		let neighbors: HashSet<TilePos> = ghosts
+			.iter()
+			.filter(|_| *machine == MachineType::Platform)
+			.flat_map(|tile| world.neighbors_of(*tile))
+			.filter(|tile| world[*tile] == Material::PLATFORM && world.is_explored(*tile))
+			.collect();
+		for tile in neighbors {
+			backend.draw_asset((2, world.platform_variant(tile, &ghosts)), tile);
+		}
+
+		let rotation = self.rotation(mouse, world);
+		for tile in tiles.iter() {
+			if ghosts.contains(tile) {
+				let sprite = match machine {
+					MachineType::Platform => (2, world.platform_variant(*tile, &ghosts)),
+					_ => conveyor_sprite(rotation, rotation.opposite()),
+				};
+				backend.draw_asset(sprite, *tile);
+				backend.fill_rect(*tile, GamePos::TILE, Colors::Ghost);
+			} else {
+				backend.fill_rect(*tile, GamePos::TILE, Colors::Invalid);
+			}
+		}
+
+		let (mineral, cost) = machine.cost();
+		let mut text = format!(
+			"{:?} x{}: {} {:?}",
+			machine,
+			ghosts.len(),
+			cost * ghosts.len(),
+			mineral
+		);
+		let invalid = tiles.len() - ghosts.len();
+		if invalid > 0 {
+			text.push_str(&format!(" ({} invalid)", invalid));
+		}
+		draw_label(backend, &text, mouse.pos());
+	}
+
+	/// Handles dragging and releasing while the Tool is active. Returns `true` if the event was consumed
+	pub fn process_event(
+		&mut self,
+		event: MouseEvent,
+		mouse: &mut Mouse,
+		entities: &mut Entities,
+		world: &mut World,
+		history: &mut History,
+	) -> bool {
+		if !self.is_active() {
+			return false;
+		}
+		match event {
+			MouseEvent::ClickDown(MouseButton::Left) => {
+				let tiles = self.tiles(mouse, world);
+				self.start = tiles.first().copied();
+			}
+			MouseEvent::ClickUp(MouseButton::Left) if self.start.is_some() => {
+				let machine = self.building.clone().expect("active BuildTool");
+				let rotation = self.rotation(mouse, world);
+				let commands: Vec<Command> = self
+					.tiles(mouse, world)
+					.into_iter()
+					.filter(|tile| {
+						self.invalid_reason(&machine, *tile, entities, world)
+							.is_none()
+					})
+					.map(|tile| match &machine {
+						MachineType::Platform => Command::PlacePlatform(tile),
+						machine => Command::PlaceMachine(tile, machine.clone(), rotation),
+					})
+					.collect();
+				if !commands.is_empty() {
+					history.execute(Command::Batch(commands), entities, world);
+				}
+				self.start = None;
+			}
+			MouseEvent::ClickDown(MouseButton::Right) => {}
+			MouseEvent::ClickUp(MouseButton::Right) => self.cancel(),
+			MouseEvent::Move(_) => {
+				mouse.on_event(event);
+			}
+			_ => return false,
+		}
+		true
+	}
+}
+
+/// Draws `text` on a Panel next to the cursor at the screen position `pos`
+fn draw_label(backend: &mut Backend, text: &str, pos: GamePos) {
+	let pos = pos + GamePos::new(16.0, 16.0);
+	let size = GamePos::new(text.chars().count() as f32 * CHAR_WIDTH + 4.0, LINE_HEIGHT);
+	backend.absolute_mode(true);
+	backend.fill_rect(pos, size, Colors::Panel);
+	backend.draw_text(text, pos + GamePos::new(2.0, 0.0), Colors::Text);
+	backend.absolute_mode(false);
+}
@@ -1,6 +1,6 @@
 use crate::{
 	ui::{Clickable, Hitbox},
-	world::GamePos,
+	world::{GamePos, TILE_SIZE},
 	Backend, BackendStyle, Colors,
 };
 
@@ -9,6 +9,8 @@ pub struct Button<T> {
 	pub identifier: T,
 	pub pos: GamePos,
 	pub size: GamePos,
+	pub icon: Option<(usize, usize)>,
+	pub enabled: bool,
 	text: String,
 }
 
@@ -18,6 +20,8 @@ impl<T> Button<T> {
 			identifier,
 			pos,
 			size,
+			icon: None,
+			enabled: true,
 			text,
 		}
 	}
@@ -25,7 +29,17 @@ impl<T> Button<T> {
 	pub fn draw(&self, backend: &mut Backend) {
 		backend.absolute_mode(true);
 		backend.fill_rect(self.pos, self.size, Colors::Button);
-		backend.draw_text(&self.text, self.pos + GamePos::UNIT, Colors::Black);
+		let mut text_pos = self.pos + GamePos::UNIT;
+		if let Some(icon) = self.icon {
+			backend.draw_asset(icon, text_pos);
+			text_pos.x += TILE_SIZE as f32 + 2.0;
+		}
+		let color = if self.enabled {
+			Colors::Black
+		} else {
+			Colors::Disabled
+		};
+		backend.draw_text(&self.text, text_pos, color);
 		backend.absolute_mode(false);
 	}
 }
@@ -37,10 +51,4 @@ impl<T> Clickable for Button<T> {
 			size: self.size,
 		}
 	}
-	fn on_context_clicked(&mut self, item: usize) -> bool {
-		panic!(
-			"Buttons don't have a Context Menu, but {} was clicked",
-			item
-		)
-	}
 }
@@ -1,12 +1,16 @@
-use crate::world::GamePos;
+use super::MenuItem;
+use crate::{world::GamePos, Command};
 
 pub trait Clickable {
 	fn hitbox(&self) -> Hitbox;
 
-	fn context_menu(&self) -> Box<dyn Iterator<Item = &(usize, &'static str)>> {
-		Box::new(std::iter::empty())
+	fn context_menu(&self) -> Vec<MenuItem> {
+		vec![]
+	}
+	fn on_context_clicked(&self, item: usize) -> Option<Command> {
+		crate::err!("Invalid Context Menu Item: {}", item);
+		None
 	}
-	fn on_context_clicked(&mut self, item: usize) -> bool;
 
 	fn intersects<O: Clickable>(&self, other: &O) -> bool {
 		self.hitbox().intersects(other.hitbox())
@@ -53,6 +57,21 @@ impl Hitbox {
 		}
 	}
 
+	/// The top left and bottom right corner
+	pub fn bounds(self) -> (GamePos, GamePos) {
+		match self {
+			Rect { pos, size } => (pos, pos + size),
+			Circle { pos, radius } => (pos - GamePos::UNIT * radius, pos + GamePos::UNIT * radius),
+		}
+	}
+
+	pub fn center(self) -> GamePos {
+		match self {
+			Rect { pos, size } => pos + size / 2.0,
+			Circle { pos, .. } => pos,
+		}
+	}
+
 	pub fn contains(self, point: GamePos) -> bool {
 		match self {
 			Rect { pos, size } => {
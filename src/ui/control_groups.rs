@@ -0,0 +1,50 @@
+use crate::{
+	entity::{Entities, WorkerID},
+	HashSet,
+};
+
+/// Worker Selections that are stored on the number keys
+#[derive(Debug)]
+pub struct ControlGroups {
+	groups: Vec<HashSet<WorkerID>>,
+	/// The last recalled group and when it was recalled
+	last_recall: Option<(usize, f32)>,
+}
+
+impl ControlGroups {
+	pub const COUNT: usize = 10;
+	/// Maximum seconds between two recalls to count as a double tap
+	const DOUBLE_TAP_TIME: f32 = 0.4;
+
+	pub fn new() -> Self {
+		Self {
+			groups: vec![HashSet::default(); Self::COUNT],
+			last_recall: None,
+		}
+	}
+
+	pub fn store(&mut self, group: usize, workers: HashSet<WorkerID>) {
+		if let Some(g) = self.groups.get_mut(group) {
+			*g = workers;
+		}
+		self.last_recall = None;
+	}
+
+	/// Returns the Workers in `group` and whether this was a double tap. `time` is in seconds
+	pub fn recall(&mut self, group: usize, time: f32) -> Option<(HashSet<WorkerID>, bool)> {
+		let workers = self.groups.get(group).filter(|g| !g.is_empty())?.clone();
+		let double_tap = match self.last_recall {
+			Some((last, t)) => last == group && time - t < Self::DOUBLE_TAP_TIME,
+			None => false,
+		};
+		self.last_recall = Some((group, time));
+		Some((workers, double_tap))
+	}
+
+	/// Removes Workers that no longer exist
+	pub fn validate(&mut self, entities: &Entities) {
+		for group in self.groups.iter_mut() {
+			group.retain(|id| entities.get_worker(*id).is_some());
+		}
+	}
+}
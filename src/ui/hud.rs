@@ -0,0 +1,123 @@
+use crate::{
+	backend::{CHAR_WIDTH, LINE_HEIGHT},
+	entity::{Entities, WorkerID},
+	world::{GamePos, Mineral},
+	Backend, BackendStyle, Colors,
+};
+
+/// The top bar that shows the Minerals, Workers and Power
+#[derive(Debug)]
+pub struct Hud {
+	screen_size: GamePos,
+	/// Position and size of the Worker figure, which cycles through idle Workers
+	worker_figure: (GamePos, GamePos),
+	last_idle: Option<WorkerID>,
+}
+
+/// What the Hud displays for a single Mineral
+#[derive(Debug, Clone, Copy)]
+pub struct MineralInfo {
+	pub mineral: Mineral,
+	pub count: usize,
+	/// Amount gained in the last minute
+	pub income: usize,
+	/// Amount spent in the last minute
+	pub spent: usize,
+}
+
+impl Hud {
+	pub const HEIGHT: f32 = LINE_HEIGHT + 4.0;
+
+	pub fn new() -> Self {
+		Self {
+			screen_size: GamePos::new(0.0, 0.0),
+			worker_figure: (GamePos::new(0.0, 0.0), GamePos::new(0.0, 0.0)),
+			last_idle: None,
+		}
+	}
+
+	pub fn set_screen_size(&mut self, width: f32, height: f32) {
+		self.screen_size = GamePos::new(width, height);
+	}
+
+	pub fn draw(
+		&mut self,
+		backend: &mut Backend,
+		minerals: &[MineralInfo],
+		entities: &Entities,
+		power: bool,
+	) {
+		backend.absolute_mode(true);
+		backend.fill_rect(
+			(0.0, 0.0),
+			(self.screen_size.x, Self::HEIGHT),
+			Colors::Panel,
+		);
+
+		let mut x = 5.0;
+		let y = 2.0;
+		for info in minerals {
+			let color = match info.mineral {
+				Mineral::Ore => Colors::Ore,
+				Mineral::Crystal => Colors::Crystal,
+				Mineral::Metal => Colors::Metal,
+			};
+			backend.fill_circle((x + 5.0, y + LINE_HEIGHT / 2.0), 5.0, color);
+			x += 14.0;
+			let text = format!(
+				"{:?}: {} (+{}/min -{}/min)",
+				info.mineral, info.count, info.income, info.spent
+			);
+			backend.draw_text(&text, (x, y), Colors::Text);
+			x += text.chars().count() as f32 * CHAR_WIDTH + 20.0;
+		}
+
+		let total = entities.workers().count();
+		let idle = idle_workers(entities).len();
+		let text = format!("Workers: {} ({} idle)", total, idle);
+		let size = GamePos::new(14.0 + text.chars().count() as f32 * CHAR_WIDTH, LINE_HEIGHT);
+		self.worker_figure = (GamePos::new(x, y), size);
+		backend.fill_rect((x + 1.0, y + 4.0), (10.0, 10.0), Colors::Worker);
+		backend.draw_text(&text, (x + 14.0, y), Colors::Text);
+		x += size.x + 20.0;
+
+		let text = if power {
+			String::from("Power: on")
+		} else {
+			String::from("Power: off (no Crystal)")
+		};
+		backend.draw_text(&text, (x, y), Colors::Text);
+
+		backend.absolute_mode(false);
+	}
+
+	/// Checks if the screen position `pos` is on the Hud
+	pub fn contains(&self, pos: GamePos) -> bool {
+		pos.y <= Self::HEIGHT
+	}
+
+	/// Returns the next idle Worker if `pos` is on the Worker figure
+	pub fn process_click(&mut self, pos: GamePos, entities: &Entities) -> Option<WorkerID> {
+		let (fig_pos, fig_size) = self.worker_figure;
+		if pos.x < fig_pos.x || pos.x > fig_pos.x + fig_size.x || !self.contains(pos) {
+			return None;
+		}
+		let idle = idle_workers(entities);
+		let next = match self.last_idle {
+			Some(last) => idle.iter().find(|id| **id > last).or_else(|| idle.first()),
+			None => idle.first(),
+		};
+		self.last_idle = next.copied();
+		self.last_idle
+	}
+}
+
+fn idle_workers(entities: &Entities) -> Vec<WorkerID> {
+	let mut idle: Vec<WorkerID> = entities
+		.workers()
+		.filter(|w| w.plan.is_empty() && w.next_target.is_none())
+		.map(|w| w.id)
+		.collect();
+	idle.sort();
+	idle
+}
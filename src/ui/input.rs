@@ -0,0 +1,356 @@
+use super::KeyCode;
+use crate::world::Dir;
+
+/// Something the player can do with the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+	Undo,
+	Redo,
+	PanUp,
+	PanDown,
+	PanLeft,
+	PanRight,
+	CenterOnSelection,
+	Follow,
+	ToggleEdgeScrolling,
+	ToggleDebug,
+	ToggleEditor,
+	BuildPlatforms,
+	Rotate,
+	Settings,
+	Quit,
+}
+use Action::*;
+
+impl Action {
+	pub fn all() -> impl DoubleEndedIterator<Item = Action> {
+		[
+			Undo,
+			Redo,
+			PanUp,
+			PanDown,
+			PanLeft,
+			PanRight,
+			CenterOnSelection,
+			Follow,
+			ToggleEdgeScrolling,
+			ToggleDebug,
+			ToggleEditor,
+			BuildPlatforms,
+			Rotate,
+			Settings,
+			Quit,
+		]
+		.iter()
+		.copied()
+	}
+
+	/// The name used in the config file
+	pub fn name(self) -> &'static str {
+		match self {
+			Undo => "undo",
+			Redo => "redo",
+			PanUp => "pan_up",
+			PanDown => "pan_down",
+			PanLeft => "pan_left",
+			PanRight => "pan_right",
+			CenterOnSelection => "center_on_selection",
+			Follow => "follow",
+			ToggleEdgeScrolling => "toggle_edge_scrolling",
+			ToggleDebug => "toggle_debug",
+			ToggleEditor => "toggle_editor",
+			BuildPlatforms => "build_platforms",
+			Rotate => "rotate",
+			Settings => "settings",
+			Quit => "quit",
+		}
+	}
+	pub fn from_name(name: &str) -> Option<Action> {
+		Action::all().find(|a| a.name() == name)
+	}
+}
+
+/// A modifier key that changes how the Mouse behaves while it is held down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Modifier {
+	#[default]
+	Shift,
+	Ctrl,
+}
+
+impl Modifier {
+	pub fn name(self) -> &'static str {
+		match self {
+			Modifier::Shift => "shift",
+			Modifier::Ctrl => "ctrl",
+		}
+	}
+	pub fn from_name(name: &str) -> Option<Modifier> {
+		match name {
+			"shift" => Some(Modifier::Shift),
+			"ctrl" => Some(Modifier::Ctrl),
+			_ => None,
+		}
+	}
+}
+
+/// A key together with the modifiers that have to be held down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+	pub key: KeyCode,
+	pub shift: bool,
+	pub ctrl: bool,
+}
+
+impl Binding {
+	pub fn new(key: KeyCode) -> Self {
+		Self {
+			key,
+			shift: false,
+			ctrl: false,
+		}
+	}
+	pub fn shift(mut self) -> Self {
+		self.shift = true;
+		self
+	}
+	pub fn ctrl(mut self) -> Self {
+		self.ctrl = true;
+		self
+	}
+
+	/// Parses the format from the config file, like `ctrl+shift+z`
+	pub fn parse(text: &str) -> Option<Binding> {
+		let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+		let key = KeyCode::parse(parts.pop()?)?;
+		let mut binding = Binding::new(key);
+		for part in parts {
+			match Modifier::from_name(&part.to_lowercase())? {
+				Modifier::Shift => binding.shift = true,
+				Modifier::Ctrl => binding.ctrl = true,
+			}
+		}
+		Some(binding)
+	}
+}
+
+impl std::fmt::Display for Binding {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.ctrl {
+			write!(f, "Ctrl+")?;
+		}
+		if self.shift {
+			write!(f, "Shift+")?;
+		}
+		write!(f, "{}", self.key)
+	}
+}
+
+/// Maps keys to Actions
+#[derive(Debug, Clone)]
+pub struct InputMap {
+	bindings: Vec<(Action, Binding)>,
+	pub brush: Modifier,
+	pub area: Modifier,
+}
+
+impl InputMap {
+	pub const CONFIG_FILE: &'static str = "input.cfg";
+	/// The hotkeys of the context Menus in `World` and `Worker`. The open Menu gets a key press
+	/// before the Bindings, unless Ctrl is held down
+	const MENU_HOTKEYS: [KeyCode; 3] =
+		[KeyCode::Delete, KeyCode::Letter('p'), KeyCode::Letter('l')];
+
+	pub fn new() -> Self {
+		use KeyCode::*;
+		let bindings = vec![
+			(Undo, Binding::new(Letter('z')).ctrl()),
+			(Redo, Binding::new(Letter('y')).ctrl()),
+			(Redo, Binding::new(Letter('z')).ctrl().shift()),
+			(PanUp, Binding::new(Arrow(Dir::Up))),
+			(PanUp, Binding::new(Letter('w'))),
+			(PanDown, Binding::new(Arrow(Dir::Down))),
+			(PanDown, Binding::new(Letter('s'))),
+			(PanLeft, Binding::new(Arrow(Dir::Left))),
+			(PanLeft, Binding::new(Letter('a'))),
+			(PanRight, Binding::new(Arrow(Dir::Right))),
+			(PanRight, Binding::new(Letter('d'))),
+			(CenterOnSelection, Binding::new(Space)),
+			(Follow, Binding::new(Letter('f'))),
+			(ToggleEdgeScrolling, Binding::new(Letter('o'))),
+			(ToggleDebug, Binding::new(Letter('h'))),
+			(ToggleEditor, Binding::new(Letter('e'))),
+			(BuildPlatforms, Binding::new(Letter('b'))),
+			(Rotate, Binding::new(Letter('r'))),
+			(Settings, Binding::new(Escape)),
+			(Quit, Binding::new(Escape).shift()),
+		];
+		Self {
+			bindings,
+			brush: Modifier::Shift,
+			area: Modifier::Ctrl,
+		}
+	}
+
+	pub fn action(&self, key: KeyCode, shift: bool, ctrl: bool) -> Option<Action> {
+		let binding = Binding { key, shift, ctrl };
+		self.bindings
+			.iter()
+			.find(|(_, b)| *b == binding)
+			.map(|(action, _)| *action)
+	}
+
+	pub fn bindings_of(&self, action: Action) -> impl Iterator<Item = Binding> + '_ {
+		self.bindings
+			.iter()
+			.filter(move |(a, _)| *a == action)
+			.map(|(_, b)| *b)
+	}
+
+	/// Replaces the first Binding of `action` with `binding`. The alternative Bindings stay
+	pub fn rebind(&mut self, action: Action, binding: Binding) {
+		if self.bindings.contains(&(action, binding)) {
+			return;
+		}
+		match self.bindings.iter().position(|(a, _)| *a == action) {
+			Some(i) => self.bindings[i].1 = binding,
+			None => self.bindings.push((action, binding)),
+		}
+	}
+
+	/// Returns all other Actions that are bound to the same key as `action`
+	pub fn conflicts_of(&self, action: Action) -> Vec<Action> {
+		let mut conflicts = vec![];
+		for binding in self.bindings_of(action) {
+			for (other, b) in self.bindings.iter() {
+				if *other != action && *b == binding && !conflicts.contains(other) {
+					conflicts.push(*other);
+				}
+			}
+		}
+		conflicts
+	}
+	/// Checks if a key of `action` is taken by a context Menu, which would get the key press first
+	pub fn menu_conflict(&self, action: Action) -> bool {
+		self.bindings_of(action)
+			.any(|b| !b.ctrl && Self::MENU_HOTKEYS.contains(&b.key))
+	}
+	/// Checks if the brush and area selection use the same Modifier
+	pub fn modifier_conflict(&self) -> bool {
+		self.brush == self.area
+	}
+
+	/// Reads a config file with one `action = binding` per line. Unknown lines are reported and skipped
+	pub fn load(text: &str) -> Self {
+		let mut map = InputMap::new();
+		let mut loaded: Vec<(Action, Binding)> = vec![];
+		for (i, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let mut split = line.splitn(2, '=').map(str::trim);
+			let (name, value) = match (split.next(), split.next()) {
+				(Some(name), Some(value)) => (name, value),
+				_ => {
+					crate::err!(
+						"{} line {}: expected `action = key`",
+						Self::CONFIG_FILE,
+						i + 1
+					);
+					continue;
+				}
+			};
+			let modifier = Modifier::from_name(&value.to_lowercase());
+			match (name, modifier) {
+				("brush", Some(modifier)) => map.brush = modifier,
+				("area", Some(modifier)) => map.area = modifier,
+				_ => match (Action::from_name(name), Binding::parse(value)) {
+					(Some(action), Some(binding)) => loaded.push((action, binding)),
+					_ => {
+						crate::err!(
+							"{} line {}: invalid binding `{}`",
+							Self::CONFIG_FILE,
+							i + 1,
+							line
+						);
+					}
+				},
+			}
+		}
+		// Actions missing from the file keep their defaults
+		map.bindings
+			.retain(|(action, _)| !loaded.iter().any(|(a, _)| a == action));
+		map.bindings.extend(loaded);
+
+		for action in Action::all() {
+			let conflicts = map.conflicts_of(action).into_iter();
+			// report every pair only once
+			for other in conflicts.filter(|other| action as usize > *other as usize) {
+				crate::err!(
+					"Conflicting bindings: {} and {}",
+					action.name(),
+					other.name()
+				);
+			}
+			if map.menu_conflict(action) {
+				crate::err!(
+					"Conflicting bindings: {} and a context Menu hotkey",
+					action.name()
+				);
+			}
+		}
+		if map.modifier_conflict() {
+			crate::err!("Conflicting bindings: brush and area use the same Modifier");
+		}
+		map
+	}
+
+	pub fn save(&self) -> String {
+		let mut text = String::new();
+		text.push_str(&format!("brush = {}\n", self.brush.name()));
+		text.push_str(&format!("area = {}\n", self.area.name()));
+		for (action, binding) in self.bindings.iter() {
+			let mut value = String::new();
+			if binding.ctrl {
+				value.push_str("ctrl+");
+			}
+			if binding.shift {
+				value.push_str("shift+");
+			}
+			value.push_str(&binding.key.name());
+			text.push_str(&format!("{} = {}\n", action.name(), value));
+		}
+		text
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rebind_keeps_alternatives() {
+		let mut map = InputMap::new();
+		let key = Binding::new(KeyCode::Letter('i'));
+		map.rebind(Action::PanUp, key);
+		let bindings: Vec<Binding> = map.bindings_of(Action::PanUp).collect();
+		assert_eq!(bindings, vec![key, Binding::new(KeyCode::Letter('w'))]);
+
+		// binding an alternative again changes nothing
+		map.rebind(Action::PanUp, Binding::new(KeyCode::Letter('w')));
+		assert_eq!(map.bindings_of(Action::PanUp).count(), 2);
+	}
+
+	#[test]
+	fn conflicts_with_menu_hotkeys() {
+		let mut map = InputMap::new();
+		assert!(Action::all().all(|action| !map.menu_conflict(action)));
+
+		map.rebind(Action::Rotate, Binding::new(KeyCode::Letter('p')));
+		assert!(map.menu_conflict(Action::Rotate));
+		// the Menus ignore keys while Ctrl is held down
+		map.rebind(Action::Rotate, Binding::new(KeyCode::Letter('p')).ctrl());
+		assert!(!map.menu_conflict(Action::Rotate));
+	}
+}
@@ -0,0 +1,219 @@
+use super::{Menu, Mouse, Selection};
+use crate::{
+	backend::{CHAR_WIDTH, LINE_HEIGHT},
+	entity::{Entities, Entity, Item, Worker},
+	world::{Fluids, GamePos, Machine, Mineral, TilePos, World},
+	Backend, BackendStyle, Colors,
+};
+
+/// Shows details about the hovered Tile or Entity and about the current Selection
+#[derive(Debug)]
+pub struct Inspector {
+	screen_size: GamePos,
+}
+
+impl Inspector {
+	pub fn new() -> Self {
+		Self {
+			screen_size: GamePos::new(0.0, 0.0),
+		}
+	}
+
+	pub fn set_screen_size(&mut self, width: f32, height: f32) {
+		self.screen_size = GamePos::new(width, height);
+	}
+
+	pub fn draw(
+		&self,
+		backend: &mut Backend,
+		mouse: &Mouse,
+		menu: &Menu,
+		entities: &Entities,
+		world: &World,
+	) {
+		let panel = selection_info(menu.selection(), entities, world);
+		if !panel.is_empty() {
+			let height = panel.len() as f32 * LINE_HEIGHT + 4.0;
+			self.draw_box(
+				backend,
+				&panel,
+				GamePos::new(5.0, self.screen_size.y - height - 5.0),
+			);
+		}
+
+		if !menu.is_hovered(mouse.pos()) {
+			let tooltip = hover_info(mouse.pos_world(), entities, world);
+			if !tooltip.is_empty() {
+				self.draw_box(backend, &tooltip, mouse.pos() + GamePos::new(16.0, 16.0));
+			}
+		}
+	}
+
+	fn draw_box(&self, backend: &mut Backend, lines: &[String], pos: GamePos) {
+		let width = lines
+			.iter()
+			.map(|line| line.chars().count() as f32 * CHAR_WIDTH)
+			.fold(0.0, f32::max)
+			+ 8.0;
+		let height = lines.len() as f32 * LINE_HEIGHT + 4.0;
+		let pos = GamePos::new(
+			pos.x.min(self.screen_size.x - width).max(0.0),
+			pos.y.min(self.screen_size.y - height).max(0.0),
+		);
+
+		backend.absolute_mode(true);
+		backend.fill_rect(pos, (width, height), Colors::Panel);
+		for (i, line) in lines.iter().enumerate() {
+			let line_pos = pos + GamePos::new(4.0, 2.0 + i as f32 * LINE_HEIGHT);
+			backend.draw_text(line, line_pos, Colors::Text);
+		}
+		backend.absolute_mode(false);
+	}
+}
+
+fn hover_info(pos: GamePos, entities: &Entities, world: &World) -> Vec<String> {
+	let tile: TilePos = pos.into();
+	if !world.is_explored(tile) {
+		return vec![];
+	}
+	match entities.entity_at(pos) {
+		Some(Entity::Worker(id)) => worker_info(entities.worker(id), entities),
+		Some(Entity::Item(id)) => item_info(entities.item(id)),
+		None => match world.machine_at(tile) {
+			Some(machine) => machine_info(machine),
+			None => tile_info(tile, world),
+		},
+	}
+}
+
+fn selection_info(selection: &Selection, entities: &Entities, world: &World) -> Vec<String> {
+	match selection {
+		Selection::Nothing => vec![],
+		Selection::Workers(workers) if workers.len() == 1 => workers
+			.iter()
+			.flat_map(|id| worker_info(entities.worker(*id), entities))
+			.collect(),
+		Selection::Workers(workers) => {
+			let mut ids: Vec<_> = workers.iter().copied().collect();
+			ids.sort();
+			let mut lines = vec![format!("{} Workers", ids.len())];
+			for id in ids {
+				let worker = entities.worker(id);
+				lines.push(format!(
+					"{} at {}: {}",
+					id,
+					worker.pos,
+					current_job(worker, entities)
+				));
+			}
+			lines
+		}
+		Selection::Item(id) => item_info(entities.item(*id)),
+		Selection::Walls(tiles) => vec![format!("{} Walls", tiles.len())],
+		Selection::Machine(pos) => world.machine_at(*pos).map(machine_info).unwrap_or_default(),
+		Selection::Air(pos) => tile_info(*pos, world),
+	}
+}
+
+fn tile_info(pos: TilePos, world: &World) -> Vec<String> {
+	let tile = match world.tile(pos) {
+		Some(tile) => tile,
+		None => return vec![],
+	};
+	let cost = match world.walk_cost(pos) {
+		Some(cost) => format!("Walk cost: {}", cost),
+		None => String::from("Solid"),
+	};
+	let materials = world.materials();
+	let mut info = vec![format!("{} at {}", materials[*tile.top()].name, pos)];
+	if tile.structure.is_some() {
+		info.push(format!("Floor: {}", materials[tile.floor].name));
+	}
+	info.push(cost);
+	if world.fluid_level(pos) > 0 {
+		info.push(format!(
+			"Level: {}/{}",
+			world.fluid_level(pos),
+			Fluids::MAX_LEVEL
+		));
+	}
+	if world.is_at_risk(pos) {
+		info.push(String::from("Unsupported: might cave in"));
+	}
+	info
+}
+
+fn machine_info(machine: &Machine) -> Vec<String> {
+	let source = match machine.power_source() {
+		Some(pos) => format!("{}", pos),
+		None if machine.is_power_source() => String::from("self"),
+		None => String::from("none"),
+	};
+	let cycle = match machine.cycle_progress() {
+		Some((progress, total)) => format!("{}/{} ticks", progress, total),
+		None => String::from("idle"),
+	};
+	let power = if machine.power() {
+		"on"
+	} else if machine.is_flooded() {
+		"short-circuited by Water"
+	} else {
+		"off"
+	};
+	let mut lines = vec![
+		format!("{} at {}", machine.machine_type.info().name, machine.pos),
+		format!("Facing: {:?}", machine.rotation),
+		format!("Power: {}", power),
+		format!("Power source: {}", source),
+		format!("Production: {}", cycle),
+	];
+	if let Some((mineral, stored, capacity)) = machine.stored_input() {
+		lines.push(format!("Stored: {}/{} {:?}", stored, capacity, mineral));
+	}
+	for mineral in Mineral::all().filter(|m| machine.capacity(*m) > 0) {
+		lines.push(format!(
+			"{:?}: {}/{}",
+			mineral,
+			machine.stock(mineral),
+			machine.capacity(mineral)
+		));
+	}
+	lines
+}
+
+fn worker_info(worker: &Worker, entities: &Entities) -> Vec<String> {
+	let mut energy = format!("{}/{}", worker.energy, Worker::MAX_ENERGY);
+	if worker.is_exhausted() {
+		energy.push_str(" (exhausted)");
+	} else if worker.is_tired() {
+		energy.push_str(" (tired)");
+	}
+	let item = match &worker.item {
+		Some(item) => format!("{:?}", item.mineral),
+		None => String::from("nothing"),
+	};
+	vec![
+		format!("{} at {}", worker.id, worker.pos),
+		format!("Health: {}/{}", worker.health, Worker::MAX_HEALTH),
+		format!("Energy: {}", energy),
+		format!("Job: {}", current_job(worker, entities)),
+		format!("Plan: {} Jobs", worker.plan.len()),
+		format!("Carrying: {}", item),
+	]
+}
+
+fn current_job(worker: &Worker, entities: &Entities) -> String {
+	worker
+		.plan
+		.first()
+		.and_then(|id| entities.get_job(*id))
+		.map(|job| job.variant().to_string())
+		.unwrap_or_else(|| String::from("idle"))
+}
+
+fn item_info(item: &Item) -> Vec<String> {
+	vec![
+		format!("{}", item.id),
+		format!("Mineral: {:?}", item.mineral),
+	]
+}
@@ -1,6 +1,6 @@
 use crate::world::Dir;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyCode {
 	Number(usize),
 	Letter(char),
@@ -11,3 +11,71 @@ pub enum KeyCode {
 	Delete,
 	Arrow(Dir),
 }
+
+impl std::fmt::Display for KeyCode {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		use KeyCode::*;
+		match self {
+			Number(n) => write!(f, "{}", n),
+			Letter(c) => write!(f, "{}", c.to_ascii_uppercase()),
+			Space => write!(f, "Space"),
+			Escape => write!(f, "Esc"),
+			Enter => write!(f, "Enter"),
+			Backspace => write!(f, "Backspace"),
+			Delete => write!(f, "Del"),
+			Arrow(dir) => write!(f, "{:?}", dir),
+		}
+	}
+}
+
+impl KeyCode {
+	/// Letters are always bound in lower case, even if Shift is held down
+	pub fn normalized(self) -> KeyCode {
+		match self {
+			KeyCode::Letter(c) => KeyCode::Letter(c.to_ascii_lowercase()),
+			key => key,
+		}
+	}
+
+	/// The name used in config files
+	pub fn name(self) -> String {
+		use KeyCode::*;
+		match self {
+			Number(n) => n.to_string(),
+			Letter(c) => c.to_ascii_lowercase().to_string(),
+			Space => "space".to_string(),
+			Escape => "escape".to_string(),
+			Enter => "enter".to_string(),
+			Backspace => "backspace".to_string(),
+			Delete => "delete".to_string(),
+			Arrow(dir) => format!("{:?}", dir).to_lowercase(),
+		}
+	}
+	pub fn parse(name: &str) -> Option<KeyCode> {
+		use KeyCode::*;
+		let name = name.to_lowercase();
+		let key = match name.as_str() {
+			"space" => Space,
+			"escape" | "esc" => Escape,
+			"enter" => Enter,
+			"backspace" => Backspace,
+			"delete" | "del" => Delete,
+			"up" => Arrow(Dir::Up),
+			"down" => Arrow(Dir::Down),
+			"left" => Arrow(Dir::Left),
+			"right" => Arrow(Dir::Right),
+			_ => {
+				let mut chars = name.chars();
+				let c = chars.next()?;
+				if chars.next().is_some() {
+					return None;
+				}
+				match c.to_digit(10) {
+					Some(n) => Number(n as usize),
+					None => Letter(c),
+				}
+			}
+		};
+		Some(key)
+	}
+}
@@ -1,36 +1,94 @@
 use crate::{
-	backend::TEXT_SIZE,
+	backend::{CHAR_WIDTH, LINE_HEIGHT, TEXT_SIZE},
 	entity::{Entities, ItemID, WorkerID},
-	ui::{Button, Clickable},
-	world::{GamePos, TilePos, World},
-	Backend, BackendStyle, Colors, HashSet,
+	ui::{Button, Clickable, KeyCode, MenuItem},
+	world::{GamePos, TilePos, World, TILE_SIZE},
+	Backend, BackendStyle, Colors, Command, HashSet, History,
 };
 
 #[derive(Debug)]
 pub struct Menu {
 	selection: Selection,
-	context_menu: Vec<Button<usize>>,
-	pos: f32,
-	width: f32,
+	items: Vec<MenuItem>,
+	/// Indices of the opened Submenus, starting at the top level
+	open: Vec<usize>,
+	/// Identified by the path of indices to their MenuItem
+	context_menu: Vec<Button<Vec<usize>>>,
+	anchor: GamePos,
+	screen_size: GamePos,
 }
 
 impl Menu {
 	pub fn new() -> Self {
 		Self {
 			selection: Selection::Nothing,
+			items: vec![],
+			open: vec![],
 			context_menu: vec![],
-			pos: 0.0,
-			width: 0.0,
+			anchor: GamePos::new(0.0, 0.0),
+			screen_size: GamePos::new(0.0, 0.0),
 		}
 	}
 
-	pub fn set_pos(&mut self, width: f32) {
-		self.width = (width / 10.0).min(200.0);
-		self.pos = width - self.width - 5.0;
+	pub fn set_screen_size(&mut self, width: f32, height: f32) {
+		self.screen_size = GamePos::new(width, height);
+		self.layout();
+	}
+
+	/// Sets the screen position where the next Context Menu pops up
+	pub fn open_at(&mut self, pos: GamePos) {
+		self.anchor = pos + GamePos::new(5.0, 5.0);
+	}
+
+	fn layout(&mut self) {
+		self.context_menu.clear();
+
+		let mut items = &self.items;
+		let mut path = vec![];
+		let mut pos = self.anchor;
+		loop {
+			if items.is_empty() {
+				break;
+			}
+			let width = items
+				.iter()
+				.map(|item| {
+					let icon = if item.icon.is_some() {
+						TILE_SIZE as f32 + 2.0
+					} else {
+						0.0
+					};
+					item.label().chars().count() as f32 * CHAR_WIDTH + icon + 4.0
+				})
+				.fold(0.0, f32::max);
+			let height = items.len() as f32 * LINE_HEIGHT;
 
-		for (i, button) in self.context_menu.iter_mut().enumerate() {
-			button.pos = GamePos::new(self.pos, 5.0 + i as f32 * (TEXT_SIZE as f32 + 3.0));
-			button.size = GamePos::new(self.width, TEXT_SIZE as f32 + 2.0);
+			// keep the Menu on the screen
+			pos.x = pos.x.min(self.screen_size.x - width).max(0.0);
+			pos.y = pos.y.min(self.screen_size.y - height).max(0.0);
+
+			for (i, item) in items.iter().enumerate() {
+				let mut id = path.clone();
+				id.push(i);
+				let mut button = Button::new(
+					id,
+					item.label(),
+					GamePos::new(pos.x, pos.y + i as f32 * LINE_HEIGHT),
+					GamePos::new(width, TEXT_SIZE as f32 + 2.0),
+				);
+				button.icon = item.icon;
+				button.enabled = item.is_enabled();
+				self.context_menu.push(button);
+			}
+
+			match self.open.get(path.len()) {
+				Some(&i) if i < items.len() => {
+					pos = GamePos::new(pos.x + width + 2.0, pos.y + i as f32 * LINE_HEIGHT);
+					items = &items[i].children;
+					path.push(i);
+				}
+				_ => break,
+			}
 		}
 	}
 
@@ -61,27 +119,28 @@ impl Menu {
 
 	pub fn set_selection(&mut self, selection: Selection, entities: &Entities, world: &World) {
 		self.selection = selection;
-		self.context_menu = match &self.selection {
-			Nothing => Box::new(std::iter::empty()),
+		self.items = match &self.selection {
+			Nothing => vec![],
 			Workers(workers) => workers
 				.iter()
 				.next()
 				.map(|id| entities.worker(*id).context_menu())
-				.unwrap_or_else(|| Box::new(std::iter::empty())),
+				.unwrap_or_default(),
 			Item(id) => entities.item(*id).context_menu(),
 			Walls(tiles) => world.context_menu_walls(tiles),
 			Machine(pos) | Air(pos) => world.context_menu_tile(*pos),
+		};
+		self.open.clear();
+		self.layout();
+	}
+
+	fn item(&self, path: &[usize]) -> Option<&MenuItem> {
+		let (&first, rest) = path.split_first()?;
+		let mut item = self.items.get(first)?;
+		for &i in rest {
+			item = item.children.get(i)?;
 		}
-		.enumerate()
-		.map(|(i, &(identifier, text))| {
-			Button::new(
-				identifier,
-				String::from(text),
-				GamePos::new(self.pos, 5.0 + i as f32 * (TEXT_SIZE as f32 + 3.0)),
-				GamePos::new(self.width, TEXT_SIZE as f32 + 2.0),
-			)
-		})
-		.collect();
+		Some(item)
 	}
 
 	pub fn process_click(
@@ -89,35 +148,123 @@ impl Menu {
 		pos: GamePos,
 		entities: &mut Entities,
 		world: &mut World,
+		history: &mut History,
 	) -> bool {
-		for button in self.context_menu.iter() {
-			if button.contains(pos) {
-				let clear = match &self.selection {
-					Nothing => panic!("Why was there a Button when nothing is selected??"),
-					Workers(workers) => {
-						let mut clear = false;
-						for id in workers {
-							clear = entities
-								.worker_mut(*id)
-								.on_context_clicked(button.identifier)
-								|| clear;
-						}
-						clear
-					}
-					Item(id) => entities.item_mut(*id).on_context_clicked(button.identifier),
-					Walls(tiles) => world.context_click_walls(tiles, button.identifier),
-					Machine(pos) | Air(pos) => world.context_click_tile(*pos, button.identifier),
-				};
-				if clear {
-					self.set_selection(Selection::Nothing, entities, world);
+		let path = match self.context_menu.iter().find(|b| b.contains(pos)) {
+			Some(button) => button.identifier.clone(),
+			None => return false,
+		};
+		let item = match self.item(&path) {
+			Some(item) => item,
+			None => return true,
+		};
+		if item.is_submenu() {
+			if self.open.starts_with(&path) {
+				self.open.truncate(path.len() - 1);
+			} else {
+				self.open = path;
+			}
+			self.layout();
+		} else if item.is_enabled() {
+			let id = item.id;
+			self.activate(id, entities, world, history);
+		}
+		true
+	}
+
+	/// Activates the entry with the Hotkey `code` in the open Menus. Returns `false` if there is none
+	pub fn process_key(
+		&mut self,
+		code: KeyCode,
+		entities: &mut Entities,
+		world: &mut World,
+		history: &mut History,
+	) -> bool {
+		let mut found = None;
+		let mut items = &self.items;
+		for depth in 0..=self.open.len() {
+			if let Some(item) = items
+				.iter()
+				.find(|item| item.hotkey == Some(code) && item.is_enabled() && !item.is_submenu())
+			{
+				// deeper Menus take priority
+				found = Some(item.id);
+			}
+			match self.open.get(depth).and_then(|&i| items.get(i)) {
+				Some(item) => items = &item.children,
+				None => break,
+			}
+		}
+		match found {
+			Some(id) => {
+				self.activate(id, entities, world, history);
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn activate(
+		&mut self,
+		id: usize,
+		entities: &mut Entities,
+		world: &mut World,
+		history: &mut History,
+	) {
+		let command = match &self.selection {
+			Nothing => None,
+			Workers(workers) => {
+				let commands: Vec<_> = workers
+					.iter()
+					.filter_map(|worker| entities.worker(*worker).on_context_clicked(id))
+					.collect();
+				if commands.is_empty() {
+					None
 				} else {
-					let sel = self.take_selection();
-					self.set_selection(sel, entities, world);
+					Some(Command::Batch(commands))
 				}
-				return true;
 			}
+			Item(item) => entities.item(*item).on_context_clicked(id),
+			Walls(tiles) => world.context_click_walls(tiles, id),
+			Machine(pos) | Air(pos) => world.context_click_tile(*pos, id),
+		};
+		if let Some(command) = command {
+			history.execute(command, entities, world);
+			self.set_selection(Selection::Nothing, entities, world);
+		} else {
+			let sel = self.take_selection();
+			self.set_selection(sel, entities, world);
+		}
+	}
+
+	/// Removes Workers and Items that no longer exist from the Selection
+	pub fn validate_selection(&mut self, entities: &Entities, world: &World) {
+		let changed = match &mut self.selection {
+			Workers(workers) => {
+				let len = workers.len();
+				workers.retain(|id| entities.get_worker(*id).is_some());
+				workers.len() != len
+			}
+			Item(id) => entities.get_item(*id).is_none(),
+			_ => false,
+		};
+		if !changed {
+			return;
 		}
-		false
+		let sel = match self.take_selection() {
+			Workers(workers) if !workers.is_empty() => Workers(workers),
+			_ => Nothing,
+		};
+		self.set_selection(sel, entities, world);
+	}
+
+	/// Checks if the screen position `pos` is on a Button of the Context Menu
+	pub fn is_hovered(&self, pos: GamePos) -> bool {
+		self.context_menu.iter().any(|b| b.contains(pos))
+	}
+
+	pub fn selection(&self) -> &Selection {
+		&self.selection
 	}
 	pub fn take_selection(&mut self) -> Selection {
 		std::mem::replace(&mut self.selection, Selection::Nothing)
@@ -134,3 +281,38 @@ pub enum Selection {
 	Air(TilePos),
 }
 use Selection::*;
+
+impl Selection {
+	/// The top left and bottom right corner of everything that is selected
+	pub fn bounds(&self, entities: &Entities) -> Option<(GamePos, GamePos)> {
+		let hitboxes: Vec<(GamePos, GamePos)> = match self {
+			Nothing => vec![],
+			Workers(workers) => workers
+				.iter()
+				.filter_map(|id| entities.get_worker(*id))
+				.map(|w| w.hitbox().bounds())
+				.collect(),
+			Item(id) => entities
+				.get_item(*id)
+				.map(|item| item.hitbox().bounds())
+				.into_iter()
+				.collect(),
+			Walls(tiles) => tiles
+				.iter()
+				.map(|tile| (GamePos::from(*tile), GamePos::from(*tile) + GamePos::TILE))
+				.collect(),
+			Machine(pos) | Air(pos) => {
+				vec![(GamePos::from(*pos), GamePos::from(*pos) + GamePos::TILE)]
+			}
+		};
+		hitboxes.into_iter().fold(None, |bounds, (tl, br)| {
+			Some(match bounds {
+				None => (tl, br),
+				Some((min, max)) => (
+					GamePos::new(min.x.min(tl.x), min.y.min(tl.y)),
+					GamePos::new(max.x.max(br.x), max.y.max(br.y)),
+				),
+			})
+		})
+	}
+}
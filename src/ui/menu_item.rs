@@ -0,0 +1,76 @@
+use super::KeyCode;
+
+/// An entry of a Context Menu
+#[derive(Debug, Clone)]
+pub struct MenuItem {
+	pub id: usize,
+	pub text: String,
+	/// Asset tile drawn in front of the text
+	pub icon: Option<(usize, usize)>,
+	pub hotkey: Option<KeyCode>,
+	/// The reason why this entry can't be clicked right now
+	pub disabled: Option<String>,
+	pub children: Vec<MenuItem>,
+}
+
+impl MenuItem {
+	pub fn new<S: Into<String>>(id: usize, text: S) -> Self {
+		Self {
+			id,
+			text: text.into(),
+			icon: None,
+			hotkey: None,
+			disabled: None,
+			children: vec![],
+		}
+	}
+	/// An entry that opens a nested Menu with `children`
+	pub fn submenu<S: Into<String>>(text: S, children: Vec<MenuItem>) -> Self {
+		Self {
+			children,
+			..Self::new(std::usize::MAX, text)
+		}
+	}
+
+	pub fn icon(mut self, tile: (usize, usize)) -> Self {
+		self.icon = Some(tile);
+		self
+	}
+	pub fn hotkey(mut self, key: KeyCode) -> Self {
+		self.hotkey = Some(key);
+		self
+	}
+	pub fn disabled<S: Into<String>>(mut self, reason: S) -> Self {
+		self.disabled = Some(reason.into());
+		self
+	}
+	pub fn disabled_if<S: Into<String>>(self, condition: bool, reason: S) -> Self {
+		if condition {
+			self.disabled(reason)
+		} else {
+			self
+		}
+	}
+
+	pub fn is_submenu(&self) -> bool {
+		!self.children.is_empty()
+	}
+	pub fn is_enabled(&self) -> bool {
+		self.disabled.is_none()
+	}
+
+	/// The text shown on the Button of this entry
+	pub fn label(&self) -> String {
+		let mut label = self.text.clone();
+		if let Some(key) = &self.hotkey {
+			label += &format!(" [{}]", key);
+		}
+		if let Some(reason) = &self.disabled {
+			label += &format!(" ({})", reason);
+		}
+		if self.is_submenu() {
+			label += " >";
+		}
+		label
+	}
+}
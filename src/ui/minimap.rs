@@ -0,0 +1,157 @@
+use super::{Mouse, MouseButton, MouseEvent};
+use crate::{
+	entity::Entities,
+	world::{GamePos, Material, TilePos, World, TILE_SIZE},
+	Backend, BackendStyle, Colors,
+};
+
+/// An overview of the World in the bottom right corner
+#[derive(Debug)]
+pub struct Minimap {
+	screen_size: GamePos,
+	/// Size of the World in Tiles
+	world_size: GamePos,
+	/// Set once every Tile was drawn to the Minimap cache
+	initialized: bool,
+	dragging: bool,
+}
+
+impl Minimap {
+	/// The maximum width or height on the screen
+	pub const SIZE: f32 = 200.0;
+	const MARGIN: f32 = 5.0;
+	const FOG: (u8, u8, u8) = (15, 15, 25);
+
+	pub fn new() -> Self {
+		Self {
+			screen_size: GamePos::new(0.0, 0.0),
+			world_size: GamePos::new(1.0, 1.0),
+			initialized: false,
+			dragging: false,
+		}
+	}
+
+	pub fn set_screen_size(&mut self, width: f32, height: f32) {
+		self.screen_size = GamePos::new(width, height);
+	}
+
+	/// Pixels on the screen per Tile
+	fn scale(&self) -> f32 {
+		Self::SIZE / self.world_size.x.max(self.world_size.y)
+	}
+	/// The top left corner on the screen
+	fn pos(&self) -> GamePos {
+		self.screen_size - self.world_size * self.scale() - GamePos::new(Self::MARGIN, Self::MARGIN)
+	}
+
+	pub fn contains(&self, screen_pos: GamePos) -> bool {
+		let pos = self.pos();
+		let size = self.world_size * self.scale();
+		screen_pos.x >= pos.x
+			&& screen_pos.y >= pos.y
+			&& screen_pos.x < pos.x + size.x
+			&& screen_pos.y < pos.y + size.y
+	}
+
+	fn to_screen(&self, world_pos: GamePos) -> GamePos {
+		self.pos() + world_pos * (self.scale() / TILE_SIZE as f32)
+	}
+	fn to_world(&self, screen_pos: GamePos) -> GamePos {
+		(screen_pos - self.pos()) * (TILE_SIZE as f32 / self.scale())
+	}
+
+	pub fn draw(
+		&mut self,
+		backend: &mut Backend,
+		mouse: &Mouse,
+		entities: &Entities,
+		world: &mut World,
+	) {
+		self.world_size = GamePos::new(world.width() as f32, world.height() as f32);
+
+		let changes = world.take_visual_changes();
+		if !self.initialized {
+			self.initialized = true;
+			for y in 0..world.height() {
+				for x in 0..world.width() {
+					backend.draw_to_minimap((x, y), tile_color(world, TilePos::new(x, y)));
+				}
+			}
+		} else {
+			for pos in changes {
+				backend.draw_to_minimap((pos.x, pos.y), tile_color(world, pos));
+			}
+		}
+
+		backend.absolute_mode(true);
+
+		let pos = self.pos();
+		let size = self.world_size * self.scale();
+		backend.fill_rect(
+			pos - GamePos::new(2.0, 2.0),
+			size + GamePos::new(4.0, 4.0),
+			Colors::Panel,
+		);
+		backend.draw_minimap(pos, size);
+
+		for machine in world.machines() {
+			if machine.machine_type.is_dragged() || !world.is_explored(machine.pos) {
+				continue;
+			}
+			let (width, height) = machine.machine_type.footprint(machine.rotation);
+			let footprint = GamePos::new(width as f32, height as f32) * self.scale();
+			let tl = self.to_screen(GamePos::from(machine.pos));
+			backend.draw_asset_scaled(machine.machine_type.icon(), tl, footprint);
+		}
+
+		for worker in entities.workers() {
+			let center = self.to_screen(GamePos::from(worker.pos) + GamePos::TILE / 2.0);
+			backend.fill_rect(center - GamePos::new(1.5, 1.5), (3.0, 3.0), Colors::Worker);
+		}
+
+		// the part of the World that is on the screen
+		let tl = self.to_screen(mouse.screen_to_world(GamePos::new(0.0, 0.0)));
+		let br = self.to_screen(mouse.screen_to_world(self.screen_size));
+		let tl = GamePos::new(tl.x.max(pos.x), tl.y.max(pos.y));
+		let br = GamePos::new(br.x.min(pos.x + size.x), br.y.min(pos.y + size.y));
+		if tl.x < br.x && tl.y < br.y {
+			backend.stroke_rect(tl, br - tl, 1.0, Colors::Highlight);
+		}
+
+		backend.absolute_mode(false);
+	}
+
+	/// Moves the camera when clicking or dragging on the Minimap. Returns `true` if the event was consumed
+	pub fn process_event(&mut self, event: MouseEvent, mouse: &mut Mouse) -> bool {
+		match event {
+			MouseEvent::ClickDown(MouseButton::Left) if self.contains(mouse.pos()) => {
+				self.dragging = true;
+			}
+			MouseEvent::Move(_) if self.dragging => {
+				mouse.on_event(event);
+			}
+			MouseEvent::ClickUp(MouseButton::Left) if self.dragging => {
+				self.dragging = false;
+				return true;
+			}
+			_ => return false,
+		}
+		let pos = self.to_world(mouse.pos());
+		let max = self.world_size * TILE_SIZE as f32;
+		mouse.center_on(GamePos::new(
+			pos.x.max(0.0).min(max.x),
+			pos.y.max(0.0).min(max.y),
+		));
+		true
+	}
+}
+
+fn tile_color(world: &World, pos: TilePos) -> (u8, u8, u8) {
+	if !world.is_explored(pos) {
+		Minimap::FOG
+	} else if world[pos] == Material::MACHINE {
+		world.materials()[Material::PLATFORM].color
+	} else {
+		world.materials()[world[pos]].color
+	}
+}
@@ -1,13 +1,31 @@
+mod build_palette;
+mod build_tool;
 mod button;
 mod clickable;
+mod control_groups;
+mod hud;
+mod input;
+mod inspector;
 mod key;
 mod menu;
+mod menu_item;
+mod minimap;
 mod mouse;
+mod settings;
+pub use build_palette::*;
+pub use build_tool::*;
 pub use button::*;
 pub use clickable::*;
+pub use control_groups::*;
+pub use hud::*;
+pub use input::*;
+pub use inspector::*;
 pub use key::KeyCode;
 pub use menu::*;
+pub use menu_item::*;
+pub use minimap::*;
 pub use mouse::*;
+pub use settings::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ButtonState {
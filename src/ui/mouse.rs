@@ -1,5 +1,8 @@
-use super::ButtonState;
-use crate::{world::GamePos, Backend, BackendStyle, Colors};
+use super::{ButtonState, Modifier};
+use crate::{
+	world::{Dir, GamePos},
+	Backend, BackendStyle, Colors,
+};
 
 #[derive(Debug, Default)]
 pub struct Mouse {
@@ -15,6 +18,19 @@ pub struct Mouse {
 
 	pub scale: f32,
 	offset: GamePos,
+	screen_size: GamePos,
+	/// Size of the World in pixels. The camera is kept inside of it
+	world_size: GamePos,
+	/// The scale that the current zoom animation is moving towards
+	target_scale: f32,
+	/// The screen position that stays in place while zooming
+	zoom_anchor: GamePos,
+	/// Move the camera when the cursor is close to the edge of the screen
+	pub edge_scrolling: bool,
+	/// Set while the camera follows the selected Workers
+	following: bool,
+	brush_modifier: Modifier,
+	area_modifier: Modifier,
 }
 
 impl Mouse {
@@ -32,9 +48,110 @@ impl Mouse {
 
 			scale: 1.0,
 			offset: GamePos::new(0.0, 0.0),
+			screen_size: GamePos::new(0.0, 0.0),
+			world_size: GamePos::new(0.0, 0.0),
+			target_scale: 1.0,
+			zoom_anchor: GamePos::new(0.0, 0.0),
+			edge_scrolling: false,
+			following: false,
+			brush_modifier: Modifier::Shift,
+			area_modifier: Modifier::Ctrl,
 		}
 	}
 
+	pub const MIN_SCALE: f32 = 0.25;
+	pub const MAX_SCALE: f32 = 4.0;
+	/// Distance in pixels that the arrow keys move the camera
+	const PAN_STEP: f32 = 48.0;
+	/// Speed of edge scrolling in pixels per second
+	const EDGE_SPEED: f32 = 600.0;
+	/// Width of the area at the edge of the screen that triggers edge scrolling
+	const EDGE_SIZE: f32 = 8.0;
+	/// How much of the remaining zoom is done per second
+	const ZOOM_SPEED: f32 = 12.0;
+
+	/// Progresses the zoom animation and edge scrolling
+	pub fn update(&mut self, delta_time: f32) {
+		if self.scale != self.target_scale {
+			let t = (Self::ZOOM_SPEED * delta_time).min(1.0);
+			let mut scale = self.scale + (self.target_scale - self.scale) * t;
+			if (scale - self.target_scale).abs() < 0.001 {
+				scale = self.target_scale;
+			}
+			self.zoom_to(scale, self.zoom_anchor);
+		}
+
+		if self.edge_scrolling {
+			let mut dir = GamePos::new(0.0, 0.0);
+			if self.pos.x < Self::EDGE_SIZE {
+				dir.x = 1.0;
+			} else if self.pos.x > self.screen_size.x - Self::EDGE_SIZE {
+				dir.x = -1.0;
+			}
+			if self.pos.y < Self::EDGE_SIZE {
+				dir.y = 1.0;
+			} else if self.pos.y > self.screen_size.y - Self::EDGE_SIZE {
+				dir.y = -1.0;
+			}
+			if dir.x != 0.0 || dir.y != 0.0 {
+				self.pan(dir * Self::EDGE_SPEED * delta_time);
+			}
+		}
+	}
+
+	/// Moves the camera by `delta` pixels on the screen
+	pub fn pan(&mut self, delta: GamePos) {
+		self.following = false;
+		self.offset += delta / self.scale;
+		self.clamp();
+	}
+	/// Moves the camera one step in `dir`
+	pub fn pan_in_dir(&mut self, dir: Dir) {
+		let (x, y) = dir.as_delta();
+		self.pan(-GamePos::new(x as f32, y as f32) * Self::PAN_STEP);
+	}
+
+	/// Starts a zoom animation by `factor` around the screen position `anchor`
+	pub fn zoom(&mut self, factor: f32, anchor: GamePos) {
+		self.target_scale = (self.target_scale * factor).clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+		self.zoom_anchor = anchor;
+	}
+	/// Sets the scale immediately, keeping the world position below `anchor` in place
+	fn zoom_to(&mut self, scale: f32, anchor: GamePos) {
+		let world_pos = self.screen_to_world(anchor);
+		self.scale = scale;
+		self.offset = anchor / self.scale - world_pos;
+		self.clamp();
+	}
+
+	pub fn is_following(&self) -> bool {
+		self.following
+	}
+	pub fn set_following(&mut self, following: bool) {
+		self.following = following;
+	}
+	/// Centers the camera on the Rectangle from `tl` to `br` and zooms out until it fits on the screen
+	pub fn follow(&mut self, tl: GamePos, br: GamePos) {
+		let size = br - tl + GamePos::TILE * 4.0;
+		let fit = (self.screen_size.x / size.x).min(self.screen_size.y / size.y);
+		if fit < self.target_scale {
+			self.target_scale = fit.clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+		}
+		self.zoom_anchor = self.screen_size / 2.0;
+		self.offset = -(tl + br) / 2.0 + self.screen_size / (2.0 * self.scale);
+		self.clamp();
+	}
+
+	/// Keeps the camera inside of the World
+	fn clamp(&mut self) {
+		if self.world_size.x <= 0.0 || self.world_size.y <= 0.0 {
+			return;
+		}
+		let visible = self.screen_size / self.scale;
+		self.offset.x = clamp_axis(self.offset.x, visible.x, self.world_size.x);
+		self.offset.y = clamp_axis(self.offset.y, visible.y, self.world_size.y);
+	}
+
 	pub fn on_event(&mut self, event: MouseEvent) -> SelectionInfo {
 		use MouseEvent::*;
 		match event {
@@ -45,8 +162,7 @@ impl Mouse {
 					if self.brush_mode() {
 						return SelectionInfo::Brush(self.pos_world(), self.brush_size, true);
 					} else if !self.area_mode() || self.start_pos.is_none() {
-						let moved = delta / self.scale;
-						self.offset += moved;
+						self.pan(delta);
 					}
 				}
 				SelectionInfo::NoChange
@@ -66,7 +182,7 @@ impl Mouse {
 				if self.is_single_click && !self.brush_mode() {
 					return SelectionInfo::Click(self.pos_world());
 				}
-				if self.ctrl_down() {
+				if self.modifier_down(self.area_modifier) {
 					if let Some(start_pos) = self.start_pos.take() {
 						let pos = self.pos_world();
 						let tl = (start_pos.x.min(pos.x), start_pos.y.min(pos.y)).into();
@@ -77,25 +193,28 @@ impl Mouse {
 				SelectionInfo::NoChange
 			}
 			Scroll(delta) => {
-				let factor = 1.0 - delta / 10.0;
-				self.scale *= factor;
-
-				self.offset -= self.pos / (self.scale / factor) - self.pos / self.scale;
+				self.zoom(1.0 - delta / 10.0, self.pos);
 				SelectionInfo::NoChange
 			}
 			ClickDown(MouseButton::Right) => {
 				self.set_button(MouseButton::Right, ButtonState::Down);
-				// right mouse ignored for now
+				self.is_single_click = true;
 				SelectionInfo::NoChange
 			}
 			ClickUp(MouseButton::Right) => {
 				self.set_button(MouseButton::Right, ButtonState::Up);
-				// right mouse ignored for now
+				if self.is_single_click {
+					return SelectionInfo::Order(self.pos_world(), self.shift_down());
+				}
 				SelectionInfo::NoChange
 			}
 		}
 	}
 
+	/// The position on the screen
+	pub fn pos(&self) -> GamePos {
+		self.pos
+	}
 	pub fn pos_world(&self) -> GamePos {
 		self.screen_to_world(self.pos)
 	}
@@ -109,9 +228,9 @@ impl Mouse {
 	pub fn draw(&self, backend: &mut Backend) {
 		let pos = self.pos_world();
 
-		if self.shift_down() {
+		if self.brush_mode() {
 			backend.fill_circle(pos, self.brush_size, Colors::Cursor);
-		} else if self.ctrl_down() {
+		} else if self.area_mode() {
 			if let Some(start) = self.start_pos {
 				let tl = GamePos::new(pos.x.min(start.x), pos.y.min(start.y));
 				let br = GamePos::new(pos.x.max(start.x), pos.y.max(start.y));
@@ -122,13 +241,27 @@ impl Mouse {
 
 	pub fn set_shift(&mut self, shift: ButtonState) {
 		self.shift = shift;
+		if !self.modifier_down(self.area_modifier) {
+			self.start_pos = None;
+		}
 	}
 	pub fn set_ctrl(&mut self, ctrl: ButtonState) {
 		self.ctrl = ctrl;
-		if ctrl == ButtonState::Up {
+		if !self.modifier_down(self.area_modifier) {
 			self.start_pos = None;
 		}
 	}
+	/// Sets which Modifiers activate the brush and the area selection
+	pub fn set_modifiers(&mut self, brush: Modifier, area: Modifier) {
+		self.brush_modifier = brush;
+		self.area_modifier = area;
+	}
+	pub fn modifier_down(&self, modifier: Modifier) -> bool {
+		match modifier {
+			Modifier::Shift => self.shift_down(),
+			Modifier::Ctrl => self.ctrl_down(),
+		}
+	}
 	pub fn set_button(&mut self, button: MouseButton, state: ButtonState) {
 		match button {
 			MouseButton::Left => {
@@ -141,10 +274,10 @@ impl Mouse {
 	}
 
 	pub fn brush_mode(&self) -> bool {
-		self.shift_down()
+		self.modifier_down(self.brush_modifier)
 	}
 	pub fn area_mode(&self) -> bool {
-		self.ctrl_down() && !self.brush_mode()
+		self.modifier_down(self.area_modifier) && !self.brush_mode()
 	}
 
 	pub fn left(&self) -> ButtonState {
@@ -179,7 +312,33 @@ impl Mouse {
 		self.offset
 	}
 	pub fn set_center(&mut self, center: GamePos, screen_size: GamePos) {
-		self.offset = -center + screen_size / 2.0;
+		self.offset = -center + screen_size / (2.0 * self.scale);
+		self.clamp();
+	}
+	/// Moves the camera so that `center` is in the middle of the screen
+	pub fn center_on(&mut self, center: GamePos) {
+		self.following = false;
+		self.set_center(center, self.screen_size);
+	}
+	pub fn screen_size(&self) -> GamePos {
+		self.screen_size
+	}
+	pub fn set_screen_size(&mut self, screen_size: GamePos) {
+		self.screen_size = screen_size;
+	}
+	pub fn set_world_size(&mut self, world_size: GamePos) {
+		self.world_size = world_size;
+		self.clamp();
+	}
+}
+
+/// Clamps the offset on one axis so that no area outside of the World is visible.
+/// The World is centered if it is smaller than the screen.
+fn clamp_axis(offset: f32, visible: f32, world: f32) -> f32 {
+	if visible >= world {
+		(visible - world) / 2.0
+	} else {
+		offset.min(0.0).max(visible - world)
 	}
 }
 
@@ -189,7 +348,7 @@ pub enum MouseButton {
 	Right,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum MouseEvent {
 	Move(GamePos),
 	ClickDown(MouseButton),
@@ -203,4 +362,6 @@ pub enum SelectionInfo {
 	Click(GamePos),
 	Brush(GamePos, f32, bool),
 	Area(GamePos, GamePos),
+	/// Right click at a position. The flag is set to append to the current plan
+	Order(GamePos, bool),
 }
@@ -0,0 +1,248 @@
+use super::{Action, Binding, Button, Clickable, InputMap, KeyCode, Modifier};
+use crate::{
+	backend::{CHAR_WIDTH, LINE_HEIGHT, TEXT_SIZE},
+	world::GamePos,
+	Backend, BackendStyle, Colors,
+};
+
+/// A line on the Settings screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsEntry {
+	Action(Action),
+	Brush,
+	Area,
+	Reset,
+	Close,
+}
+
+/// The screen to rebind keys
+#[derive(Debug)]
+pub struct SettingsMenu {
+	open: bool,
+	/// The entry that waits for a key press
+	rebinding: Option<SettingsEntry>,
+	buttons: Vec<Button<SettingsEntry>>,
+	screen_size: GamePos,
+}
+
+impl SettingsMenu {
+	const WIDTH: f32 = 60.0 * CHAR_WIDTH;
+
+	pub fn new() -> Self {
+		Self {
+			open: false,
+			rebinding: None,
+			buttons: vec![],
+			screen_size: GamePos::new(0.0, 0.0),
+		}
+	}
+
+	pub fn set_screen_size(&mut self, width: f32, height: f32) {
+		self.screen_size = GamePos::new(width, height);
+	}
+
+	pub fn is_open(&self) -> bool {
+		self.open
+	}
+	pub fn open(&mut self, input: &InputMap) {
+		self.open = true;
+		self.rebinding = None;
+		self.layout(input);
+	}
+	pub fn close(&mut self) {
+		self.open = false;
+		self.rebinding = None;
+	}
+
+	fn layout(&mut self, input: &InputMap) {
+		let mut entries: Vec<(SettingsEntry, String)> = Action::all()
+			.map(|action| {
+				let bindings: Vec<String> =
+					input.bindings_of(action).map(|b| b.to_string()).collect();
+				let mut text = format!("{}: {}", action_label(action), bindings.join(", "));
+				let conflicts = input.conflicts_of(action);
+				if !conflicts.is_empty() {
+					let names: Vec<&str> = conflicts.into_iter().map(action_label).collect();
+					text.push_str(&format!("  ! conflicts with {}", names.join(", ")));
+				}
+				if input.menu_conflict(action) {
+					text.push_str("  ! taken by a context Menu");
+				}
+				(SettingsEntry::Action(action), text)
+			})
+			.collect();
+
+		let conflict = if input.modifier_conflict() {
+			"  ! conflict"
+		} else {
+			""
+		};
+		entries.push((
+			SettingsEntry::Brush,
+			format!(
+				"Brush Selection: hold {}{}",
+				modifier_label(input.brush),
+				conflict
+			),
+		));
+		entries.push((
+			SettingsEntry::Area,
+			format!(
+				"Area Selection: hold {}{}",
+				modifier_label(input.area),
+				conflict
+			),
+		));
+		entries.push((SettingsEntry::Reset, "Reset to Defaults".to_string()));
+		entries.push((SettingsEntry::Close, "Close".to_string()));
+
+		let height = entries.len() as f32 * LINE_HEIGHT;
+		let pos = (self.screen_size - GamePos::new(Self::WIDTH, height)) / 2.0;
+
+		self.buttons = entries
+			.into_iter()
+			.enumerate()
+			.map(|(i, (entry, text))| {
+				let text = match self.rebinding {
+					Some(e) if e == entry => match entry {
+						SettingsEntry::Brush | SettingsEntry::Area => {
+							"hold Shift or Ctrl...".to_string()
+						}
+						_ => "press a key... (click to cancel)".to_string(),
+					},
+					_ => text,
+				};
+				Button::new(
+					entry,
+					text,
+					GamePos::new(pos.x, pos.y + i as f32 * LINE_HEIGHT),
+					GamePos::new(Self::WIDTH, TEXT_SIZE as f32 + 2.0),
+				)
+			})
+			.collect();
+	}
+
+	pub fn draw(&self, backend: &mut Backend) {
+		if !self.open {
+			return;
+		}
+		backend.absolute_mode(true);
+		backend.fill_rect((0.0, 0.0), self.screen_size, Colors::Panel);
+		if let Some(first) = self.buttons.first() {
+			let title = first.pos - GamePos::new(0.0, LINE_HEIGHT + 4.0);
+			backend.draw_text("Key Bindings", title, Colors::Text);
+		}
+		backend.absolute_mode(false);
+
+		for button in self.buttons.iter() {
+			button.draw(backend);
+		}
+	}
+
+	/// Handles a click while the Settings are open. Returns `false` if they are closed
+	pub fn process_click(&mut self, pos: GamePos, input: &mut InputMap) -> bool {
+		if !self.open {
+			return false;
+		}
+		let entry = self
+			.buttons
+			.iter()
+			.find(|b| b.contains(pos))
+			.map(|b| b.identifier);
+		if self.rebinding.is_some() {
+			self.rebinding = None;
+		} else {
+			match entry {
+				Some(SettingsEntry::Reset) => {
+					*input = InputMap::new();
+					save(input);
+				}
+				Some(SettingsEntry::Close) => self.close(),
+				entry => self.rebinding = entry,
+			}
+		}
+		self.layout(input);
+		true
+	}
+
+	/// Handles a key press while the Settings are open. Returns `false` if the key should be
+	/// processed by the Game instead
+	pub fn process_key(
+		&mut self,
+		code: Option<KeyCode>,
+		shift: bool,
+		ctrl: bool,
+		input: &mut InputMap,
+	) -> bool {
+		if !self.open {
+			return false;
+		}
+		match (self.rebinding, code) {
+			(Some(SettingsEntry::Brush), None) | (Some(SettingsEntry::Area), None) => {
+				let modifier = match (shift, ctrl) {
+					(true, false) => Modifier::Shift,
+					(false, true) => Modifier::Ctrl,
+					_ => return true, // released or both held
+				};
+				if self.rebinding == Some(SettingsEntry::Brush) {
+					input.brush = modifier;
+				} else {
+					input.area = modifier;
+				}
+				self.rebinding = None;
+				save(input);
+			}
+			(Some(SettingsEntry::Action(action)), Some(key)) => {
+				let binding = Binding {
+					key: key.normalized(),
+					shift,
+					ctrl,
+				};
+				input.rebind(action, binding);
+				self.rebinding = None;
+				save(input);
+			}
+			(Some(_), _) | (None, None) => return true,
+			(None, Some(key)) => match input.action(key.normalized(), shift, ctrl) {
+				Some(Action::Settings) => self.close(),
+				Some(Action::Quit) => return false,
+				_ => return true,
+			},
+		}
+		if self.open {
+			self.layout(input);
+		}
+		true
+	}
+}
+
+fn save(input: &InputMap) {
+	Backend::save_config(InputMap::CONFIG_FILE, &input.save());
+}
+
+fn action_label(action: Action) -> &'static str {
+	match action {
+		Action::Undo => "Undo",
+		Action::Redo => "Redo",
+		Action::PanUp => "Move Camera Up",
+		Action::PanDown => "Move Camera Down",
+		Action::PanLeft => "Move Camera Left",
+		Action::PanRight => "Move Camera Right",
+		Action::CenterOnSelection => "Center on Selection",
+		Action::Follow => "Follow Selection",
+		Action::ToggleEdgeScrolling => "Toggle Edge Scrolling",
+		Action::ToggleDebug => "Toggle Debug View",
+		Action::ToggleEditor => "Toggle Editor Mode",
+		Action::BuildPlatforms => "Build Platforms",
+		Action::Rotate => "Rotate Building",
+		Action::Settings => "Settings",
+		Action::Quit => "Quit",
+	}
+}
+
+fn modifier_label(modifier: Modifier) -> &'static str {
+	match modifier {
+		Modifier::Shift => "Shift",
+		Modifier::Ctrl => "Ctrl",
+	}
+}
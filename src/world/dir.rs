@@ -14,6 +14,9 @@ impl Dir {
 	pub fn counter_clockwise(self) -> Dir {
 		((self.num() + 3) % 4).into()
 	}
+	pub fn opposite(self) -> Dir {
+		((self.num() + 2) % 4).into()
+	}
 	pub fn num(self) -> usize {
 		self.into()
 	}
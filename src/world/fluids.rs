@@ -0,0 +1,203 @@
+use super::{Grid, Layer, Material, Materials, TilePos};
+use crate::{HashMap, HashSet};
+
+/// Lets Water and Lava flow into open Tiles as a cellular automaton. Every flooded Tile holds a
+/// level of fluid that evens out with its neighbors
+pub struct Fluids {
+	/// How much fluid each flooded Tile holds, from 1 to `MAX_LEVEL`
+	levels: HashMap<TilePos, usize>,
+	/// Tiles whose fluid might still be able to move
+	active: HashSet<TilePos>,
+	tick: usize,
+}
+
+impl Fluids {
+	/// The level of a full Tile, like in a pocket that was never opened
+	pub const MAX_LEVEL: usize = 8;
+
+	/// Fills every fluid Tile of `grid` to the top. The fluids rest until something changes
+	pub fn new(grid: &Grid) -> Self {
+		let size = grid.size();
+		let levels = TilePos::new(0, 0)
+			.rect_iter(TilePos::new(size.x - 1, size.y - 1))
+			.filter(|pos| grid.is_fluid(*pos))
+			.map(|pos| (pos, Self::MAX_LEVEL))
+			.collect();
+		Self {
+			levels,
+			active: HashSet::default(),
+			tick: 0,
+		}
+	}
+
+	pub fn level(&self, pos: TilePos) -> usize {
+		self.levels.get(&pos).copied().unwrap_or(0)
+	}
+
+	/// Keeps the levels in sync with a Tile that was changed and wakes up the fluids around it
+	pub fn tile_changed(&mut self, pos: TilePos, grid: &Grid) {
+		if grid.is_fluid(pos) {
+			self.levels.entry(pos).or_insert(Self::MAX_LEVEL);
+		} else {
+			self.levels.remove(&pos);
+		}
+		self.active.insert(pos);
+		self.active.extend(grid.neighbors_of(pos));
+	}
+
+	/// Moves the fluids that are due in this Tick. Returns the Tiles that have to change and
+	/// their new Material
+	pub fn update(&mut self, grid: &Grid) -> Vec<(TilePos, Material)> {
+		self.tick += 1;
+		let materials = grid.materials();
+		let mut changes: HashMap<TilePos, Material> = HashMap::default();
+		let mut woken = vec![];
+
+		let mut active: Vec<TilePos> = self.active.iter().copied().collect();
+		active.sort_by_key(|pos| (pos.y, pos.x));
+		for pos in active {
+			let fluid = changes.get(&pos).copied().unwrap_or(grid[pos]);
+			let flow = match materials[fluid].flow {
+				Some(flow) => flow,
+				None => {
+					self.active.remove(&pos);
+					continue;
+				}
+			};
+			// slower fluids skip Ticks
+			let wait = self.tick % flow;
+			if wait > 0 {
+				continue;
+			}
+
+			let mut moved = false;
+			for next in grid.neighbors_of(pos) {
+				let level = self.level(pos);
+				if level == 0 {
+					break;
+				}
+				let other = changes.get(&next).copied().unwrap_or(grid[next]);
+				if let Some(lava) = Self::hardening(pos, fluid, next, other) {
+					// Lava that meets Water hardens into Rock
+					self.levels.remove(&lava);
+					changes.insert(lava, Material::ROCK);
+					woken.push(lava);
+					moved = true;
+					continue;
+				}
+				if !Self::can_flood(fluid, other, materials) || self.level(next) + 1 >= level {
+					continue;
+				}
+				*self.levels.get_mut(&pos).expect("fluid without level") -= 1;
+				*self.levels.entry(next).or_insert(0) += 1;
+				if other != fluid {
+					changes.insert(next, fluid);
+				}
+				woken.push(next);
+				moved = true;
+			}
+			if moved {
+				woken.push(pos);
+			} else {
+				self.active.remove(&pos);
+			}
+		}
+
+		for pos in woken {
+			self.active.insert(pos);
+			self.active.extend(grid.neighbors_of(pos));
+		}
+		changes.into_iter().collect()
+	}
+
+	/// Returns the Tile with the Lava if Lava and Water meet at `a` and `b`
+	fn hardening(a: TilePos, mat_a: Material, b: TilePos, mat_b: Material) -> Option<TilePos> {
+		match (mat_a, mat_b) {
+			(Material::LAVA, Material::WATER) => Some(a),
+			(Material::WATER, Material::LAVA) => Some(b),
+			_ => None,
+		}
+	}
+
+	/// Checks if `fluid` can flow onto a Tile with `mat` on top. Fluids wash away Platforms and
+	/// Conveyors, Lava also burns through Machines
+	fn can_flood(fluid: Material, mat: Material, materials: &Materials) -> bool {
+		match mat {
+			Material::PLATFORM | Material::CONVEYOR => true,
+			Material::MACHINE => fluid == Material::LAVA,
+			_ if mat == fluid => true,
+			_ => materials[mat].layer == Layer::Floor && !materials[mat].solid,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A tunnel from (1, 1) to (4, 1) in solid Rock, with `fluids` placed at its start
+	fn tunnel(fluids: &[Material]) -> Grid {
+		let mut grid = Grid::filled(6, 3, Material::ROCK, Materials::new());
+		for x in 1..=4 {
+			grid.set((x, 1), Material::AIR);
+		}
+		for (x, mat) in fluids.iter().enumerate() {
+			grid.set((x + 1, 1), *mat);
+		}
+		grid
+	}
+
+	/// Runs the fluids like the World does and applies their changes to `grid`
+	fn flow(grid: &mut Grid, ticks: usize) -> Fluids {
+		let mut fluids = Fluids::new(grid);
+		fluids.tile_changed(TilePos::new(1, 1), grid);
+		for _ in 0..ticks {
+			for (pos, mat) in fluids.update(grid) {
+				grid.set(pos, mat);
+				fluids.tile_changed(pos, grid);
+			}
+		}
+		fluids
+	}
+
+	#[test]
+	fn levels_even_out() {
+		let mut grid = tunnel(&[Material::WATER]);
+		let fluids = flow(&mut grid, 100);
+		let levels: Vec<usize> = (1..=4).map(|x| fluids.level(TilePos::new(x, 1))).collect();
+		assert_eq!(levels.iter().sum::<usize>(), Fluids::MAX_LEVEL);
+		// neighbors only differ by a single level once the fluid rests
+		for pair in levels.windows(2) {
+			assert!(
+				pair[0].max(pair[1]) - pair[0].min(pair[1]) <= 1,
+				"uneven levels {:?}",
+				levels
+			);
+		}
+		for x in 1..=4 {
+			assert_eq!(grid[(x, 1)], Material::WATER);
+		}
+		assert_eq!(fluids.level(TilePos::new(5, 1)), 0);
+	}
+
+	#[test]
+	fn lava_hardens_next_to_water() {
+		let mut grid = tunnel(&[Material::LAVA, Material::WATER]);
+		let fluids = flow(&mut grid, 10);
+		assert_eq!(grid[(1, 1)], Material::ROCK);
+		assert_eq!(fluids.level(TilePos::new(1, 1)), 0);
+		assert_eq!(grid[(2, 1)], Material::WATER);
+	}
+
+	#[test]
+	fn only_lava_floods_machines() {
+		let mut grid = tunnel(&[Material::WATER, Material::MACHINE]);
+		flow(&mut grid, 100);
+		assert_eq!(grid[(2, 1)], Material::MACHINE);
+		assert_eq!(grid[(3, 1)], Material::GROUND);
+
+		let mut grid = tunnel(&[Material::LAVA, Material::MACHINE]);
+		flow(&mut grid, 100);
+		assert_eq!(grid[(2, 1)], Material::LAVA);
+	}
+}
@@ -1,86 +1,280 @@
-use super::{Dir, Material, TilePos};
+use super::{Dir, Layer, Material, Materials, TilePos};
+use crate::HashSet;
 use hierarchical_pathfinding::prelude::{ManhattanNeighborhood, Neighborhood};
 use rand::Rng;
 
+/// The Layers of a single Tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+	pub floor: Material,
+	pub structure: Option<Material>,
+}
+
+impl Tile {
+	/// A Tile with `mat` on its Layer. Structures stand on `Ground`
+	pub fn new(mat: Material, materials: &Materials) -> Self {
+		let mut tile = Tile {
+			floor: Material::GROUND,
+			structure: None,
+		};
+		tile.place(mat, materials);
+		tile
+	}
+
+	/// The Material that is visible from above
+	pub fn top(&self) -> &Material {
+		self.structure.as_ref().unwrap_or(&self.floor)
+	}
+
+	/// Puts `mat` on its Layer. `Air` clears the structure and keeps the floor
+	pub fn place(&mut self, mat: Material, materials: &Materials) {
+		match materials[mat].layer {
+			Layer::Floor => {
+				self.structure = None;
+				if mat != Material::AIR {
+					self.floor = mat;
+				}
+			}
+			Layer::Structure => self.structure = Some(mat),
+		}
+	}
+}
+
+/// How much the Player knows about a Tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+	/// Never seen by anyone
+	Unexplored,
+	/// Seen before, but out of sight right now
+	Explored,
+	/// In sight of a Worker or Machine
+	Visible,
+}
+
 pub struct Grid {
 	width: usize,
 	height: usize,
-	grid: Vec<Vec<Material>>,
-	visible: Vec<Vec<bool>>,
+	grid: Vec<Vec<Tile>>,
+	materials: Materials,
+	visibility: Vec<Vec<Visibility>>,
+	/// The Tiles that are currently [`Visibility::Visible`]
+	in_sight: Vec<TilePos>,
 	neighborhood: ManhattanNeighborhood,
 }
 
 impl Grid {
-	pub fn new(width: usize, height: usize) -> Self {
-		let grid = vec![vec![Material::Bedrock; height]; width];
-		let visible = vec![vec![false; height]; width];
+	pub fn new(width: usize, height: usize, materials: Materials) -> Self {
+		let mut ret = Self::filled(width, height, Material::BEDROCK, materials);
+		ret.generate();
+
+		ret
+	}
 
-		let mut ret = Self {
+	/// Creates a Grid where every Tile is `mat`, without generating a Map
+	pub fn filled(width: usize, height: usize, mat: Material, materials: Materials) -> Self {
+		let grid = vec![vec![Tile::new(mat, &materials); height]; width];
+		let visibility = vec![vec![Visibility::Unexplored; height]; width];
+
+		Self {
 			width,
 			height,
 			grid,
-			visible,
+			materials,
+			visibility,
+			in_sight: vec![],
 			neighborhood: ManhattanNeighborhood::new(width, height),
-		};
-		ret.generate();
-
-		ret
+		}
 	}
 
 	pub fn size(&self) -> TilePos {
 		TilePos::new(self.width, self.height)
 	}
+	pub fn materials(&self) -> &Materials {
+		&self.materials
+	}
 
+	/// The topmost Material at `pos`
 	pub fn get<T: Into<TilePos>>(&self, pos: T) -> Option<Material> {
+		self.tile(pos).map(|tile| *tile.top())
+	}
+	pub fn tile<T: Into<TilePos>>(&self, pos: T) -> Option<Tile> {
 		let TilePos { x, y } = pos.into();
 		self.grid.get(x).and_then(|v| v.get(y).copied())
 	}
+	pub fn floor<T: Into<TilePos>>(&self, pos: T) -> Option<Material> {
+		self.tile(pos).map(|tile| tile.floor)
+	}
+	pub fn structure<T: Into<TilePos>>(&self, pos: T) -> Option<Material> {
+		self.tile(pos).and_then(|tile| tile.structure)
+	}
+
+	/// Puts `mat` on its Layer at `pos`. See [`Tile::place`]
+	pub fn set<T: Into<TilePos>>(&mut self, pos: T, mat: Material) {
+		let TilePos { x, y } = pos.into();
+		self.grid[x][y].place(mat, &self.materials);
+	}
+	pub fn set_tile<T: Into<TilePos>>(&mut self, pos: T, tile: Tile) {
+		let TilePos { x, y } = pos.into();
+		self.grid[x][y] = tile;
+	}
 
 	pub fn is_solid<T: Into<TilePos>>(&self, pos: T) -> bool {
 		match self.get(pos) {
-			Some(m) => m.is_solid(),
+			Some(m) => self.materials[m].solid,
+			None => true,
+		}
+	}
+
+	/// Checks if the Tile holds up the ceiling. The edge of the World always does
+	pub fn supports<T: Into<TilePos>>(&self, pos: T) -> bool {
+		match self.get(pos) {
+			Some(m) => self.materials[m].supports(),
 			None => true,
 		}
 	}
 
+	pub fn is_fluid<T: Into<TilePos>>(&self, pos: T) -> bool {
+		match self.get(pos) {
+			Some(m) => self.materials[m].is_fluid(),
+			None => false,
+		}
+	}
+
 	pub fn walk_cost<T: Into<TilePos>>(&self, pos: T) -> Option<usize> {
-		self.get(pos).and_then(Material::walk_cost)
+		self.get(pos).and_then(|m| self.materials[m].walk_cost())
 	}
 
-	pub fn is_visible<T: Into<TilePos>>(&self, pos: T) -> bool {
+	pub fn visibility<T: Into<TilePos>>(&self, pos: T) -> Visibility {
 		let pos: TilePos = pos.into();
-		self.visible
+		self.visibility
 			.get(pos.x)
 			.and_then(|v| v.get(pos.y).copied())
-			.unwrap_or(false)
+			.unwrap_or(Visibility::Unexplored)
+	}
+	/// Checks if the Tile has ever been seen. Only explored Tiles can be worked on
+	pub fn is_explored<T: Into<TilePos>>(&self, pos: T) -> bool {
+		self.visibility(pos) != Visibility::Unexplored
 	}
-	pub fn set_visible<T: Into<TilePos>>(&mut self, pos: T) {
-		let pos: TilePos = pos.into();
-		if self.is_visible(pos) {
-			return;
-		}
 
-		self.visible[pos.x][pos.y] = true;
+	/// Makes everything in sight of `eyes` visible, where each eye has a position and a sight
+	/// radius. Returns the Tiles that were explored for the first time
+	pub fn update_vision(&mut self, eyes: &[(TilePos, usize)]) -> Vec<TilePos> {
+		for pos in std::mem::take(&mut self.in_sight) {
+			self.visibility[pos.x][pos.y] = Visibility::Explored;
+		}
+		let mut explored = vec![];
+		for (origin, radius) in eyes.iter() {
+			for pos in self.field_of_view(*origin, *radius) {
+				let visibility = &mut self.visibility[pos.x][pos.y];
+				match *visibility {
+					Visibility::Visible => continue,
+					Visibility::Unexplored => explored.push(pos),
+					Visibility::Explored => {}
+				}
+				*visibility = Visibility::Visible;
+				self.in_sight.push(pos);
+			}
+		}
+		explored
+	}
 
-		if self.is_solid(pos) {
+	/// The Tiles within `radius` of `origin` that are not hidden behind solid Tiles, using
+	/// recursive shadow casting
+	pub fn field_of_view(&self, origin: TilePos, radius: usize) -> Vec<TilePos> {
+		// transforms from the first octant to the others
+		const OCTANTS: [(isize, isize, isize, isize); 8] = [
+			(1, 0, 0, 1),
+			(0, 1, 1, 0),
+			(0, -1, 1, 0),
+			(-1, 0, 0, 1),
+			(-1, 0, 0, -1),
+			(0, -1, -1, 0),
+			(0, 1, -1, 0),
+			(1, 0, 0, -1),
+		];
+		let mut ret = vec![origin];
+		for octant in OCTANTS.iter() {
+			self.cast_light(origin, radius as isize, 1, 1.0, 0.0, *octant, &mut ret);
+		}
+		ret
+	}
+	#[allow(clippy::too_many_arguments)]
+	fn cast_light(
+		&self,
+		origin: TilePos,
+		radius: isize,
+		row: isize,
+		mut start: f32,
+		end: f32,
+		(xx, xy, yx, yy): (isize, isize, isize, isize),
+		out: &mut Vec<TilePos>,
+	) {
+		if start < end {
 			return;
 		}
-
-		let mut next = vec![pos];
-
-		while let Some(p) = next.pop() {
-			for n in self.neighbors_of(p) {
-				if self.is_visible(n) {
+		let mut new_start = 0.0;
+		for distance in row..=radius {
+			let dy = -distance;
+			let mut blocked = false;
+			for dx in -distance..=0 {
+				let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+				let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+				if start < right_slope {
 					continue;
+				} else if end > left_slope {
+					break;
+				}
+				let x = origin.x as isize + dx * xx + dy * xy;
+				let y = origin.y as isize + dx * yx + dy * yy;
+				let inside =
+					x >= 0 && x < self.width as isize && y >= 0 && y < self.height as isize;
+				let pos = TilePos::new(x.max(0) as usize, y.max(0) as usize);
+				if inside && dx * dx + dy * dy <= radius * radius {
+					out.push(pos);
 				}
-				self.visible[n.x][n.y] = true;
-				if !self.is_solid(n) {
-					next.push(n);
+				let opaque = !inside || self.is_solid(pos);
+				if blocked {
+					if opaque {
+						new_start = right_slope;
+					} else {
+						blocked = false;
+						start = new_start;
+					}
+				} else if opaque && distance < radius {
+					blocked = true;
+					let mult = (xx, xy, yx, yy);
+					self.cast_light(origin, radius, distance + 1, start, left_slope, mult, out);
+					new_start = right_slope;
 				}
 			}
+			if blocked {
+				break;
+			}
 		}
 	}
 
+	/// Returns up to `count` explored, walkable Tiles around `start`, ordered by distance
+	pub fn walkable_around(&self, start: TilePos, count: usize) -> Vec<TilePos> {
+		let mut ret = vec![];
+		let mut seen = HashSet::default();
+		let mut next = std::collections::VecDeque::new();
+		seen.insert(start);
+		next.push_back(start);
+
+		while let Some(pos) = next.pop_front() {
+			if ret.len() == count {
+				break;
+			}
+			ret.push(pos);
+			for n in self.neighbors_of(pos) {
+				if self.is_explored(n) && self.walk_cost(n).is_some() && seen.insert(n) {
+					next.push_back(n);
+				}
+			}
+		}
+		ret
+	}
+
 	pub fn neighbors_of<T: Into<(usize, usize)>>(&self, pos: T) -> impl Iterator<Item = TilePos> {
 		self.neighborhood
 			.get_all_neighbors(pos.into())
@@ -110,7 +304,12 @@ impl Grid {
 	}
 
 	pub fn generate(&mut self) {
-		use Material::*;
+		let (ground, bedrock, granite, rock) = (
+			Material::GROUND,
+			Material::BEDROCK,
+			Material::GRANITE,
+			Material::ROCK,
+		);
 		let (width, height) = (self.width, self.height);
 
 		let num_tiles = (width * height) as f64;
@@ -123,7 +322,7 @@ impl Grid {
 		for _ in 0..cave_count {
 			let x = rng.gen_range(0, width);
 			let y = rng.gen_range(0, height);
-			self[(x, y)] = Air;
+			self.set((x, y), ground);
 		}
 
 		let radius = 2;
@@ -133,70 +332,82 @@ impl Grid {
 				if (mid.x as isize - x as isize).pow(2) + (mid.y as isize - y as isize).pow(2)
 					< (radius * radius) as isize
 				{
-					self[(x, y)] = Air;
+					self.set((x, y), ground);
 				}
 			}
 		}
 
-		self.grow(Air, Bedrock, Air, 1.0);
-		self.grow(Air, Bedrock, Air, 1.0);
-		self.grow(Air, Bedrock, Air, 0.8);
-		self.grow(Air, Bedrock, Air, 0.6);
-		self.grow(Air, Bedrock, Air, 0.4);
-		self.grow(Air, Bedrock, Air, 0.4);
-
-		self.grow(Rock, Bedrock, Air, 1.0);
-		self.grow(Rock, Bedrock, Rock, 0.6);
-		self.grow(Rock, Bedrock, Rock, 0.5);
-		self.grow(Rock, Bedrock, Rock, 0.3);
-		self.grow(Rock, Bedrock, Rock, 0.3);
-
-		self.grow(Rock, Air, Rock, 0.2);
-
-		self.grow(Granite, Bedrock, Rock, 0.8);
-		self.grow(Granite, Bedrock, Granite, 0.4);
-		self.grow(Granite, Bedrock, Granite, 0.4);
-		self.grow(Granite, Rock, Rock, 0.03);
-		self.grow(Granite, Rock, Granite, 0.1);
-
-		let min = num_tiles / 64.0;
-		let max = num_tiles / 48.0;
-		let ore_count = rng.gen_range(min, max) as i32;
-		for _ in 0..ore_count {
-			let x = rng.gen_range(0, width);
-			let y = rng.gen_range(0, height);
-			if self[(x, y)] == Rock {
-				self[(x, y)] = Ore;
+		self.grow(ground, bedrock, ground, 1.0);
+		self.grow(ground, bedrock, ground, 1.0);
+		self.grow(ground, bedrock, ground, 0.8);
+		self.grow(ground, bedrock, ground, 0.6);
+		self.grow(ground, bedrock, ground, 0.4);
+		self.grow(ground, bedrock, ground, 0.4);
+
+		self.grow(rock, bedrock, ground, 1.0);
+		self.grow(rock, bedrock, rock, 0.6);
+		self.grow(rock, bedrock, rock, 0.5);
+		self.grow(rock, bedrock, rock, 0.3);
+		self.grow(rock, bedrock, rock, 0.3);
+
+		self.grow(rock, ground, rock, 0.2);
+
+		self.grow(granite, bedrock, rock, 0.8);
+		self.grow(granite, bedrock, granite, 0.4);
+		self.grow(granite, bedrock, granite, 0.4);
+		self.grow(granite, rock, rock, 0.03);
+		self.grow(granite, rock, granite, 0.1);
+
+		let veins: Vec<_> = self
+			.materials
+			.all()
+			.filter_map(|mat| {
+				let info = &self.materials[mat];
+				info.vein_spacing
+					.map(|spacing| (mat, spacing, info.vein_growth))
+			})
+			.collect();
+		for (mat, (closest, farthest), growth) in veins {
+			let min = num_tiles / farthest as f64;
+			let max = num_tiles / closest as f64;
+			let vein_count = rng.gen_range(min, max) as i32;
+			for _ in 0..vein_count {
+				let x = rng.gen_range(0, width);
+				let y = rng.gen_range(0, height);
+				if self[(x, y)] == rock {
+					self.set((x, y), mat);
+				}
 			}
-		}
 
-		self.grow(Ore, Rock, Ore, 0.23);
-		self.grow(Ore, Rock, Ore, 0.23);
+			self.grow(mat, rock, mat, growth);
+			self.grow(mat, rock, mat, growth);
+		}
 
-		let min = num_tiles / 80.0;
-		let max = num_tiles / 64.0;
-		let ore_count = rng.gen_range(min, max) as i32;
-		for _ in 0..ore_count {
-			let x = rng.gen_range(0, width);
-			let y = rng.gen_range(0, height);
-			if self[(x, y)] == Rock {
-				self[(x, y)] = Crystal;
+		// fluids stay behind walls until they are mined open
+		let mut leaks = vec![];
+		for x in 0..width {
+			for y in 0..height {
+				if self.is_fluid((x, y))
+					&& self
+						.neighbors_of((x, y))
+						.any(|p| !self.is_solid(p) && !self.is_fluid(p))
+				{
+					leaks.push((x, y));
+				}
 			}
 		}
-
-		self.grow(Crystal, Rock, Crystal, 0.14);
-		self.grow(Crystal, Rock, Crystal, 0.14);
+		for pos in leaks {
+			self.set(pos, rock);
+		}
 
 		for x in 0..width {
-			self[(x, 0)] = Bedrock;
-			self[(x, height - 1)] = Bedrock;
+			self.set((x, 0), bedrock);
+			self.set((x, height - 1), bedrock);
 		}
 		for y in 0..height {
-			self[(0, y)] = Bedrock;
-			self[(width - 1, y)] = Bedrock;
+			self.set((0, y), bedrock);
+			self.set((width - 1, y), bedrock);
 		}
-
-		self.set_visible(mid);
 	}
 
 	fn grow(&mut self, material: Material, src: Material, neighbor: Material, odd_increase: f64) {
@@ -221,21 +432,114 @@ impl Grid {
 			}
 		}
 		for (x, y) in changes {
-			self[(x, y)] = material;
+			self.set((x, y), material);
 		}
 	}
 }
 
 impl<T: Into<TilePos>> std::ops::Index<T> for Grid {
 	type Output = Material;
+	/// The topmost Material at `index`
 	fn index(&self, index: T) -> &Material {
 		let TilePos { x, y } = index.into();
-		&self.grid[x][y]
+		self.grid[x][y].top()
 	}
 }
-impl<T: Into<TilePos>> std::ops::IndexMut<T> for Grid {
-	fn index_mut(&mut self, index: T) -> &mut Material {
-		let TilePos { x, y } = index.into();
-		&mut self.grid[x][y]
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn structure_stays_on_floor() {
+		let materials = Materials::new();
+		let mut tile = Tile::new(Material::AIR, &materials);
+		tile.place(Material::ROCK, &materials);
+		assert_eq!(tile.floor, Material::GROUND);
+		assert_eq!(tile.structure, Some(Material::ROCK));
+		assert_eq!(*tile.top(), Material::ROCK);
+	}
+
+	#[test]
+	fn air_uncovers_floor() {
+		let materials = Materials::new();
+		let mut tile = Tile::new(Material::GRANITE, &materials);
+		tile.place(Material::AIR, &materials);
+		assert_eq!(tile.floor, Material::GROUND);
+		assert_eq!(tile.structure, None);
+		assert_eq!(*tile.top(), Material::GROUND);
+	}
+
+	#[test]
+	fn debris_on_floor() {
+		let materials = Materials::new();
+		let mut tile = Tile::new(Material::AIR, &materials);
+		tile.place(Material::DEBRIS, &materials);
+		assert_eq!(tile.floor, Material::GROUND);
+		assert_eq!(tile.structure, Some(Material::DEBRIS));
+	}
+
+	#[test]
+	fn removed_platform_restores_floor() {
+		let materials = Materials::load(
+			"[Gravel]\nlayer = floor\nsolid = false\nwalk_cost = 3\ncolor = 1, 2, 3\n",
+		);
+		let gravel = materials.get("Gravel").unwrap();
+		let mut tile = Tile::new(gravel, &materials);
+		assert_eq!(tile.floor, gravel);
+		assert_eq!(tile.structure, None);
+
+		tile.place(Material::PLATFORM, &materials);
+		assert_eq!(*tile.top(), Material::PLATFORM);
+		tile.place(Material::AIR, &materials);
+		assert_eq!(tile.floor, gravel);
+		assert_eq!(*tile.top(), gravel);
+	}
+
+	#[test]
+	fn sight_radius() {
+		let grid = Grid::filled(15, 15, Material::AIR, Materials::new());
+		let seen = grid.field_of_view(TilePos::new(7, 7), 3);
+		assert!(seen.contains(&TilePos::new(7, 7)));
+		assert!(seen.contains(&TilePos::new(10, 7)));
+		assert!(seen.contains(&TilePos::new(7, 4)));
+		assert!(seen.contains(&TilePos::new(9, 9)));
+		assert!(!seen.contains(&TilePos::new(11, 7)));
+		assert!(!seen.contains(&TilePos::new(10, 10)));
+	}
+
+	#[test]
+	fn walls_block_sight() {
+		let mut grid = Grid::filled(15, 15, Material::AIR, Materials::new());
+		for y in 0..15 {
+			grid.set((9, y), Material::ROCK);
+		}
+		let seen = grid.field_of_view(TilePos::new(7, 7), 5);
+		// the wall itself can be seen, but nothing behind it
+		assert!(seen.contains(&TilePos::new(9, 7)));
+		assert!(!seen.contains(&TilePos::new(10, 7)));
+		assert!(!seen.contains(&TilePos::new(11, 6)));
+		assert!(seen.contains(&TilePos::new(5, 7)));
+	}
+
+	#[test]
+	fn vision_leaves_explored_tiles() {
+		let mut grid = Grid::filled(15, 15, Material::AIR, Materials::new());
+		let pos = TilePos::new(7, 7);
+		let explored = grid.update_vision(&[(pos, 2)]);
+		assert!(explored.contains(&pos));
+		assert_eq!(grid.visibility(pos), Visibility::Visible);
+		assert_eq!(grid.visibility((0, 0)), Visibility::Unexplored);
+
+		// the Worker walked away
+		let explored = grid.update_vision(&[(TilePos::new(0, 0), 1)]);
+		assert!(!explored.contains(&pos));
+		assert_eq!(grid.visibility(pos), Visibility::Explored);
+		assert_eq!(grid.visibility((0, 0)), Visibility::Visible);
+
+		// coming back only makes the Tiles visible again
+		let explored = grid.update_vision(&[(pos, 2)]);
+		assert!(explored.is_empty());
+		assert_eq!(grid.visibility(pos), Visibility::Visible);
 	}
 }
@@ -0,0 +1,127 @@
+use super::{Grid, TilePos};
+use crate::HashMap;
+
+/// A light source with its position, radius and Color
+pub type Light = (TilePos, usize, (u8, u8, u8));
+
+/// How brightly each Tile is lit. The background cache is tinted with it
+pub struct Lighting {
+	tints: Vec<Vec<(u8, u8, u8)>>,
+	/// The light that reaches each Tile. Only the Tiles in `lit` differ from `AMBIENT`
+	light: Vec<Vec<[f32; 3]>>,
+	/// The Tiles that were lit in the last update
+	lit: Vec<TilePos>,
+	/// The lights of the last update, sorted by position
+	lights: Vec<Light>,
+	/// Tiles made of a glowing Material and their Color
+	glows: HashMap<TilePos, (u8, u8, u8)>,
+	/// A Tile near a light started or stopped blocking it
+	dirty: bool,
+	/// Tiles whose tint changed since the last call to `take_changes`
+	changes: Vec<TilePos>,
+}
+
+impl Lighting {
+	/// The brightness of Tiles that no light reaches
+	const AMBIENT: f32 = 0.3;
+
+	/// Finds the glowing Tiles of `grid`. Nothing is lit until the first update
+	pub fn new(grid: &Grid) -> Self {
+		let size = grid.size();
+		let dark = Self::to_tint([Self::AMBIENT; 3]);
+		let materials = grid.materials();
+		let glows = TilePos::new(0, 0)
+			.rect_iter(TilePos::new(size.x - 1, size.y - 1))
+			.filter_map(|pos| materials[grid[pos]].glow.map(|color| (pos, color)))
+			.collect();
+		Self {
+			tints: vec![vec![dark; size.y]; size.x],
+			light: vec![vec![[Self::AMBIENT; 3]; size.y]; size.x],
+			lit: vec![],
+			lights: vec![],
+			glows,
+			dirty: true,
+			changes: vec![],
+		}
+	}
+
+	pub fn tint(&self, pos: TilePos) -> (u8, u8, u8) {
+		self.tints[pos.x][pos.y]
+	}
+
+	/// The glowing Tiles and the Color of their light
+	pub fn glows(&self) -> impl Iterator<Item = (TilePos, (u8, u8, u8))> + '_ {
+		self.glows.iter().map(|(pos, color)| (*pos, *color))
+	}
+
+	/// Keeps the glowing Tiles in sync with a Tile that was changed. The light map is only
+	/// recomputed if the Tile is in reach of a light and `was_solid` no longer holds
+	pub fn tile_changed(&mut self, pos: TilePos, was_solid: bool, grid: &Grid) {
+		match grid.materials()[grid[pos]].glow {
+			Some(color) => self.glows.insert(pos, color),
+			None => self.glows.remove(&pos),
+		};
+		if grid.is_solid(pos) == was_solid {
+			return;
+		}
+		let in_reach = |(origin, radius, _): &Light| {
+			let dx = (pos.x as isize - origin.x as isize).abs();
+			let dy = (pos.y as isize - origin.y as isize).abs();
+			dx.max(dy) <= *radius as isize
+		};
+		if self.lights.iter().any(in_reach) {
+			self.dirty = true;
+		}
+	}
+
+	/// Recomputes the light map if `lights` or the Tiles around them changed. Light fades
+	/// towards the edge of its radius and is blocked by solid Tiles
+	pub fn update(&mut self, grid: &Grid, mut lights: Vec<Light>) {
+		lights.sort_by_key(|(pos, radius, color)| (pos.x, pos.y, *radius, *color));
+		if !self.dirty && lights == self.lights {
+			return;
+		}
+		self.dirty = false;
+
+		let old_lit = std::mem::take(&mut self.lit);
+		for pos in old_lit.iter() {
+			self.light[pos.x][pos.y] = [Self::AMBIENT; 3];
+		}
+		for (origin, radius, (r, g, b)) in lights.iter() {
+			let mut lit = grid.field_of_view(*origin, *radius);
+			lit.sort_by_key(|pos| (pos.x, pos.y));
+			lit.dedup();
+			for pos in lit {
+				let dx = pos.x as f32 - origin.x as f32;
+				let dy = pos.y as f32 - origin.y as f32;
+				let falloff = 1.0 - (dx * dx + dy * dy).sqrt() / (*radius as f32 + 1.0);
+				let tile = &mut self.light[pos.x][pos.y];
+				for (channel, color) in tile.iter_mut().zip([r, g, b].iter()) {
+					*channel += **color as f32 / 255.0 * falloff.max(0.0);
+				}
+				self.lit.push(pos);
+			}
+		}
+		self.lit.sort_by_key(|pos| (pos.x, pos.y));
+		self.lit.dedup();
+		self.lights = lights;
+
+		// Tiles that went dark and Tiles that are lit now
+		for pos in old_lit.into_iter().chain(self.lit.iter().copied()) {
+			let tint = Self::to_tint(self.light[pos.x][pos.y]);
+			if self.tints[pos.x][pos.y] != tint {
+				self.tints[pos.x][pos.y] = tint;
+				self.changes.push(pos);
+			}
+		}
+	}
+
+	pub fn take_changes(&mut self) -> Vec<TilePos> {
+		std::mem::take(&mut self.changes)
+	}
+
+	fn to_tint(rgb: [f32; 3]) -> (u8, u8, u8) {
+		let channel = |c: f32| (c.min(1.0) * 255.0) as u8;
+		(channel(rgb[0]), channel(rgb[1]), channel(rgb[2]))
+	}
+}
@@ -1,103 +1,627 @@
-use super::{Dir, TilePos, World, TILE_SIZE};
+use super::{Dir, GamePos, Material, Mineral, RectIter, TilePos, World, TILE_SIZE};
 use crate::HashSet;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MachineType {
 	Spawn,
 	Lab,
 	ConstructionSite(Box<MachineType>),
 	Platform,
+	Refinery,
+	Depot,
+	/// Moves Items towards its `rotation`
+	Conveyor,
+	/// Holds up the ceiling of dug out areas
+	Pillar,
+	/// Recharges the energy of Workers
+	Charger,
 }
 use MachineType::*;
 
+/// How a Machine takes part in the Power network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Power {
+	/// Powers all Machines that are connected to it
+	Source,
+	/// Only works with Power and can only be built next to powered Machines
+	Consumer,
+	/// Passes Power on and can be built anywhere
+	Conductor,
+	/// Works without Power
+	Unused,
+}
+
+/// Something the Player can tell a Machine to do from its context menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineAction {
+	/// Starts a production cycle
+	Produce,
+	Research,
+}
+
+/// What a Machine makes at the end of a production cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Product {
+	Worker,
+	Mineral(Mineral),
+}
+
+/// A production cycle that takes `ticks` while the Machine has Power
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+	pub ticks: usize,
+	/// The Minerals that Workers have to deliver for every cycle
+	pub input: Option<(Mineral, usize)>,
+	pub product: Product,
+	/// Start the next cycle right away instead of waiting for [`MachineAction::Produce`]
+	pub repeat: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortKind {
+	Input,
+	Output,
+}
+
+/// A side of a Machine where things go in or come out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Port {
+	pub kind: PortKind,
+	/// The Tile in the footprint of the unrotated Machine
+	pub offset: (usize, usize),
+	/// The side of the Tile that the Port faces
+	pub side: Dir,
+}
+
+/// How a kind of Machine looks and behaves
+#[derive(Debug, Clone)]
+pub struct MachineInfo {
+	pub name: &'static str,
+	/// The Position of the sprite in the assets
+	pub sprite: (usize, usize),
+	/// The width and height of the unrotated Machine in Tiles
+	pub footprint: (usize, usize),
+	pub ports: &'static [Port],
+	/// The Minerals needed to build the Machine
+	pub cost: (Mineral, usize),
+	pub power: Power,
+	pub actions: &'static [(MachineAction, &'static str)],
+	pub cycle: Option<Cycle>,
+	/// How many of each Mineral the Machine can hold for the Player
+	pub storage: &'static [(Mineral, usize)],
+}
+
+const SPAWN: MachineInfo = MachineInfo {
+	name: "Spawn",
+	sprite: (1, 0),
+	footprint: (1, 1),
+	ports: &[Port {
+		kind: PortKind::Output,
+		offset: (0, 0),
+		side: Dir::Down,
+	}],
+	cost: (Mineral::Crystal, 20),
+	power: Power::Source,
+	actions: &[(MachineAction::Produce, "Spawn Worker")],
+	cycle: Some(Cycle {
+		ticks: 25,
+		input: None,
+		product: Product::Worker,
+		repeat: false,
+	}),
+	storage: &[
+		(Mineral::Ore, 20),
+		(Mineral::Crystal, 20),
+		(Mineral::Metal, 20),
+	],
+};
+const LAB: MachineInfo = MachineInfo {
+	name: "Lab",
+	sprite: (1, 1),
+	footprint: (1, 1),
+	ports: &[],
+	cost: (Mineral::Ore, 10),
+	power: Power::Consumer,
+	actions: &[(MachineAction::Research, "Research")],
+	cycle: None,
+	storage: &[],
+};
+const CONSTRUCTION_SITE: MachineInfo = MachineInfo {
+	name: "Construction Site",
+	sprite: (1, 2),
+	footprint: (1, 1),
+	ports: &[],
+	cost: (Mineral::Ore, 0),
+	power: Power::Unused,
+	actions: &[],
+	cycle: None,
+	storage: &[],
+};
+const PLATFORM: MachineInfo = MachineInfo {
+	name: "Platform",
+	sprite: (2, 0),
+	footprint: (1, 1),
+	ports: &[],
+	cost: (Mineral::Ore, 1),
+	power: Power::Conductor,
+	actions: &[],
+	cycle: None,
+	storage: &[],
+};
+const REFINERY: MachineInfo = MachineInfo {
+	name: "Refinery",
+	sprite: (1, 4),
+	footprint: (2, 2),
+	ports: &[
+		Port {
+			kind: PortKind::Input,
+			offset: (0, 1),
+			side: Dir::Left,
+		},
+		Port {
+			kind: PortKind::Output,
+			offset: (1, 1),
+			side: Dir::Right,
+		},
+	],
+	cost: (Mineral::Ore, 15),
+	power: Power::Consumer,
+	actions: &[],
+	cycle: Some(Cycle {
+		ticks: 20,
+		input: Some((Mineral::Ore, 1)),
+		product: Product::Mineral(Mineral::Metal),
+		repeat: true,
+	}),
+	storage: &[],
+};
+const DEPOT: MachineInfo = MachineInfo {
+	name: "Depot",
+	sprite: (1, 5),
+	footprint: (2, 2),
+	ports: &[],
+	cost: (Mineral::Ore, 10),
+	power: Power::Unused,
+	actions: &[],
+	cycle: None,
+	storage: &[
+		(Mineral::Ore, 100),
+		(Mineral::Crystal, 100),
+		(Mineral::Metal, 50),
+	],
+};
+const CHARGER: MachineInfo = MachineInfo {
+	name: "Charger",
+	sprite: (1, 3),
+	footprint: (1, 1),
+	ports: &[],
+	cost: (Mineral::Metal, 5),
+	power: Power::Consumer,
+	actions: &[],
+	cycle: None,
+	storage: &[],
+};
+const PILLAR: MachineInfo = MachineInfo {
+	name: "Pillar",
+	sprite: (3, 13),
+	footprint: (1, 1),
+	ports: &[],
+	cost: (Mineral::Ore, 2),
+	power: Power::Unused,
+	actions: &[],
+	cycle: None,
+	storage: &[],
+};
+const CONVEYOR: MachineInfo = MachineInfo {
+	name: "Conveyor",
+	sprite: (3, 1),
+	footprint: (1, 1),
+	ports: &[],
+	cost: (Mineral::Ore, 1),
+	power: Power::Unused,
+	actions: &[],
+	cycle: None,
+	storage: &[],
+};
+
 impl MachineType {
-	pub fn num(&self) -> usize {
-		match *self {
-			Spawn => 0,
-			Lab => 1,
-			ConstructionSite(..) => 2,
-			Platform => panic!("Platform has no number"),
+	pub fn info(&self) -> &'static MachineInfo {
+		match self {
+			Spawn => &SPAWN,
+			Lab => &LAB,
+			ConstructionSite(..) => &CONSTRUCTION_SITE,
+			Platform => &PLATFORM,
+			Refinery => &REFINERY,
+			Depot => &DEPOT,
+			Conveyor => &CONVEYOR,
+			Pillar => &PILLAR,
+			Charger => &CHARGER,
+		}
+	}
+
+	/// All Machines that the Player can build
+	pub fn all_buildable() -> Vec<MachineType> {
+		vec![
+			Platform, Conveyor, Spawn, Lab, Refinery, Depot, Pillar, Charger,
+		]
+	}
+
+	/// Machines that are placed in lines or rectangles by dragging
+	pub fn is_dragged(&self) -> bool {
+		matches!(self, Platform | Conveyor)
+	}
+
+	/// Machines that Workers recharge their energy at while they have Power
+	pub fn charges_workers(&self) -> bool {
+		matches!(self, Spawn | Charger)
+	}
+
+	/// The Material of the Tiles that the Machine covers
+	pub fn material(&self) -> Material {
+		match self {
+			Platform => Material::PLATFORM,
+			Conveyor => Material::CONVEYOR,
+			_ => Material::MACHINE,
+		}
+	}
+
+	/// The Position of the sprite in the assets
+	pub fn icon(&self) -> (usize, usize) {
+		self.info().sprite
+	}
+
+	/// Checks if this Machine only works next to a powered Machine
+	pub fn needs_power(&self) -> bool {
+		self.info().power == Power::Consumer
+	}
+
+	/// The Minerals needed to build this Machine
+	pub fn cost(&self) -> (Mineral, usize) {
+		match self {
+			ConstructionSite(machine) => machine.cost(),
+			machine => machine.info().cost,
+		}
+	}
+
+	/// The width and height in Tiles when the Machine faces `rotation`
+	pub fn footprint(&self, rotation: Dir) -> (usize, usize) {
+		let (width, height) = match self {
+			ConstructionSite(machine) => machine.info().footprint,
+			machine => machine.info().footprint,
+		};
+		if matches!(rotation, Dir::Up | Dir::Down) {
+			(width, height)
+		} else {
+			(height, width)
 		}
 	}
 }
 
+/// The sprite of a Conveyor that moves Items towards `dir` after they came in from the side `from`
+pub fn conveyor_sprite(dir: Dir, from: Dir) -> (usize, usize) {
+	let shape = if from == dir.counter_clockwise() {
+		1
+	} else if from == dir.clockwise() {
+		2
+	} else {
+		0
+	};
+	(3, 1 + dir.num() * 3 + shape)
+}
+
+/// Rotates `offset` in a footprint of `size` clockwise until `Up` faces `rotation`
+fn rotate_offset(offset: (usize, usize), size: (usize, usize), rotation: Dir) -> (usize, usize) {
+	let (mut x, mut y) = offset;
+	let (mut width, mut height) = size;
+	for _ in 0..rotation.num() {
+		let new_x = height - 1 - y;
+		y = x;
+		x = new_x;
+		std::mem::swap(&mut width, &mut height);
+	}
+	(x, y)
+}
+
+/// The Tile in the rotated footprint and the side that `port` faces
+fn rotate_port(port: &Port, size: (usize, usize), rotation: Dir) -> ((usize, usize), Dir) {
+	let mut side = port.side;
+	for _ in 0..rotation.num() {
+		side = side.clockwise();
+	}
+	(rotate_offset(port.offset, size, rotation), side)
+}
+
 #[derive(Debug)]
 pub struct Machine {
 	pub pos: TilePos,
 	pub machine_type: MachineType,
-	power_source: Option<Dir>,
+	/// The direction that the top side of the unrotated Machine faces
+	pub rotation: Dir,
+	/// The neighboring Tile that the Power comes from
+	power_source: Option<TilePos>,
 	power: bool,
-	cooldown: Option<usize>,
+	/// Water next to the Machine cuts its Power
+	flooded: bool,
+	/// The Ticks spent on the current production cycle
+	cycle: Option<usize>,
+	/// The delivered input Minerals of the production cycle
+	stored: usize,
+	/// The Minerals held for the Player, indexed by [`Mineral::num`]
+	storage: Vec<usize>,
 }
 
 impl Machine {
-	pub fn new(pos: TilePos, machine_type: MachineType) -> Self {
+	/// How many cycles worth of input can be stored
+	const INPUT_CYCLES: usize = 5;
+	/// How many Tiles far the Machine can see
+	pub const SIGHT: usize = 4;
+	/// How far a powered Machine lights up its surroundings
+	pub const LIGHT_RADIUS: usize = 5;
+	pub const LIGHT_COLOR: (u8, u8, u8) = (255, 250, 230);
+
+	pub fn new(pos: TilePos, machine_type: MachineType, rotation: Dir) -> Self {
 		Self {
 			pos,
 			machine_type,
+			rotation,
 			power_source: None,
 			power: false,
-			cooldown: None,
+			flooded: false,
+			cycle: None,
+			stored: 0,
+			storage: vec![0; Mineral::count()],
+		}
+	}
+
+	/// The Tiles covered by the Machine. `pos` is the top left one
+	pub fn tiles(&self) -> RectIter {
+		let (width, height) = self.machine_type.footprint(self.rotation);
+		self.pos
+			.rect_iter(self.pos + TilePos::new(width - 1, height - 1))
+	}
+	pub fn covers(&self, tile: TilePos) -> bool {
+		let (width, height) = self.machine_type.footprint(self.rotation);
+		tile.x >= self.pos.x
+			&& tile.y >= self.pos.y
+			&& tile.x < self.pos.x + width
+			&& tile.y < self.pos.y + height
+	}
+	/// The Tiles around the Machine
+	pub fn border(&self, world: &World) -> Vec<TilePos> {
+		let mut border = vec![];
+		for tile in self.tiles() {
+			for n in world.neighbors_of(tile) {
+				if !self.covers(n) && !border.contains(&n) {
+					border.push(n);
+				}
+			}
 		}
+		border
+	}
+	/// The rotated Ports as the covered Tile, the side it faces and its kind
+	pub fn ports(&self) -> Vec<(TilePos, Dir, PortKind)> {
+		let size = self.machine_type.info().footprint;
+		self.machine_type
+			.info()
+			.ports
+			.iter()
+			.map(|port| {
+				let ((x, y), side) = rotate_port(port, size, self.rotation);
+				(self.pos + TilePos::new(x, y), side, port.kind)
+			})
+			.collect()
 	}
 
 	pub fn draw(&self, backend: &mut crate::Backend) {
 		use crate::{BackendStyle, Colors};
-		if self.machine_type != MachineType::Platform {
-			backend.draw_asset((1, self.machine_type.num()), self.pos);
+		let (width, height) = self.machine_type.footprint(self.rotation);
+		let size = GamePos::new(width as f32, height as f32) * TILE_SIZE as f32;
+		if !self.machine_type.is_dragged() {
+			for tile in self.tiles() {
+				backend.draw_asset(self.machine_type.icon(), tile);
+			}
+			if width * height > 1 {
+				backend.stroke_rect(self.pos, size, 1.0, Colors::Black);
+			}
 		}
+		self.draw_ports(backend);
 		if !self.power {
-			backend.fill_rect(
-				self.pos,
-				(TILE_SIZE as f32, TILE_SIZE as f32),
-				Colors::NoPower,
-			);
+			backend.fill_rect(self.pos, size, Colors::NoPower);
 			backend.draw_asset((3, 0), self.pos);
 		}
 	}
+	/// Marks the Ports as small squares on the edges of the Machine
+	pub fn draw_ports(&self, backend: &mut crate::Backend) {
+		use crate::{BackendStyle, Colors};
+		for (tile, side, kind) in self.ports() {
+			let color = match kind {
+				PortKind::Input => Colors::InputPort,
+				PortKind::Output => Colors::OutputPort,
+			};
+			let (dx, dy) = side.as_delta();
+			let center = GamePos::from(tile) + GamePos::TILE / 2.0;
+			let edge = center + GamePos::new(dx as f32, dy as f32) * (TILE_SIZE as f32 / 2.0 - 2.0);
+			backend.fill_rect(edge - GamePos::new(2.0, 2.0), (4.0, 4.0), color);
+		}
+	}
+
+	/// Updates the Power and the production cycle. Returns what was produced in this Tick
+	pub fn update(&mut self, spawn_has_power: bool, flooded: bool) -> Option<Product> {
+		let info = self.machine_type.info();
+		self.flooded = flooded && info.power != Power::Unused;
+		self.power = info.power == Power::Unused
+			|| (self.has_power_source() && spawn_has_power && !self.flooded);
+
+		let cycle = info.cycle?;
+		if !self.power {
+			return None;
+		}
+		if self.cycle.is_none() && cycle.repeat {
+			self.start_cycle();
+		}
+		let progress = self.cycle.as_mut()?;
+		*progress += 1;
+		if *progress < cycle.ticks {
+			return None;
+		}
+		self.cycle = None;
+		Some(cycle.product)
+	}
 
-	pub fn update(&mut self, spawn_has_power: bool) {
-		self.power = matches!(self.machine_type, ConstructionSite(..))
-			|| (self.has_power_source() && spawn_has_power);
+	/// Starts a production cycle and uses up its input. Returns `false` if the Machine has no
+	/// cycle, is already busy or is missing input
+	pub fn start_cycle(&mut self) -> bool {
+		let cycle = match self.machine_type.info().cycle {
+			Some(cycle) if self.cycle.is_none() => cycle,
+			_ => return false,
+		};
+		if let Some((_, amount)) = cycle.input {
+			if self.stored < amount {
+				return false;
+			}
+			self.stored -= amount;
+		}
+		self.cycle = Some(0);
+		true
+	}
+	pub fn cancel_cycle(&mut self) {
+		if self.cycle.take().is_none() {
+			return;
+		}
+		let input = self.machine_type.info().cycle.and_then(|c| c.input);
+		if let Some((_, amount)) = input {
+			self.stored += amount;
+		}
 	}
 
 	pub fn remove(&mut self) {}
 
-	pub fn is_spawn(&self) -> bool {
-		self.machine_type == MachineType::Spawn
+	pub fn is_power_source(&self) -> bool {
+		self.machine_type.info().power == Power::Source
+	}
+
+	pub fn power(&self) -> bool {
+		self.power
+	}
+	pub fn is_flooded(&self) -> bool {
+		self.flooded
+	}
+	pub fn power_source(&self) -> Option<TilePos> {
+		self.power_source
+	}
+	/// Checks if Workers can deliver `mineral` as input for the production cycle
+	pub fn accepts(&self, mineral: Mineral) -> bool {
+		match self.stored_input() {
+			Some((input, stored, capacity)) => input == mineral && stored < capacity,
+			None => false,
+		}
+	}
+	pub fn store(&mut self, mineral: Mineral) {
+		if self.accepts(mineral) {
+			self.stored += 1;
+		}
+	}
+	/// The input Mineral of the production cycle, how many are stored and how many fit in
+	pub fn stored_input(&self) -> Option<(Mineral, usize, usize)> {
+		let (mineral, amount) = self.machine_type.info().cycle?.input?;
+		Some((mineral, self.stored, amount * Self::INPUT_CYCLES))
+	}
+	/// How many of `mineral` the Machine can hold for the Player
+	pub fn capacity(&self, mineral: Mineral) -> usize {
+		self.machine_type
+			.info()
+			.storage
+			.iter()
+			.find(|(m, _)| *m == mineral)
+			.map(|(_, capacity)| *capacity)
+			.unwrap_or(0)
+	}
+	pub fn stock(&self, mineral: Mineral) -> usize {
+		self.storage[mineral.num()]
+	}
+	pub fn has_room(&self, mineral: Mineral) -> bool {
+		self.stock(mineral) < self.capacity(mineral)
+	}
+	/// Puts a Mineral into storage. Returns `false` if it is full
+	pub fn deposit(&mut self, mineral: Mineral) -> bool {
+		if !self.has_room(mineral) {
+			return false;
+		}
+		self.storage[mineral.num()] += 1;
+		true
+	}
+	/// Takes up to `amount` of `mineral` out of storage. Returns how many were taken
+	pub fn withdraw(&mut self, mineral: Mineral, amount: usize) -> usize {
+		let taken = amount.min(self.stock(mineral));
+		self.storage[mineral.num()] -= taken;
+		taken
+	}
+	/// Empties the storage and returns what was in it
+	pub fn take_storage(&mut self) -> Vec<(Mineral, usize)> {
+		let contents = Mineral::all()
+			.map(|m| (m, self.stock(m)))
+			.filter(|(_, amount)| *amount > 0)
+			.collect();
+		self.storage = vec![0; Mineral::count()];
+		contents
+	}
+	/// The Tiles where Workers deliver input. Any Tile around the Machine if it has no Input Ports
+	pub fn entrances(&self, world: &World) -> Vec<TilePos> {
+		let inputs: Vec<TilePos> = self
+			.ports()
+			.into_iter()
+			.filter(|(_, _, kind)| *kind == PortKind::Input)
+			.filter_map(|(tile, side, _)| world.tile_in_dir(tile, side))
+			.collect();
+		if inputs.is_empty() {
+			self.border(world)
+		} else {
+			inputs
+		}
+	}
+
+	/// The Ticks spent on the current production cycle and the Ticks it takes in total
+	pub fn cycle_progress(&self) -> Option<(usize, usize)> {
+		let cycle = self.machine_type.info().cycle?;
+		self.cycle.map(|progress| (progress, cycle.ticks))
 	}
 
 	pub fn has_power_source(&self) -> bool {
-		self.is_spawn() || self.power_source.is_some()
+		self.is_power_source() || self.power_source.is_some()
 	}
-	pub fn set_power_source(&mut self, source: Option<Dir>) {
+	pub fn set_power_source(&mut self, source: Option<TilePos>) {
 		self.power_source = source;
 	}
-	pub fn find_power_source(&self, world: &World) -> Option<Dir> {
-		Dir::all().find_map(|dir| {
-			let mut pos = world.tile_in_dir(self.pos, dir)?;
+	pub fn find_power_source(&self, world: &World) -> Option<TilePos> {
+		self.border(world).into_iter().find(|start| {
+			let mut pos = *start;
 			let mut seen = HashSet::default();
-			while seen.insert(pos) {
-				let machine = world.machine_at(pos)?;
-				if machine.is_spawn() {
-					return Some(dir);
+			seen.insert(self.pos);
+			while let Some(machine) = world.machine_at(pos) {
+				if !seen.insert(machine.pos) {
+					return false;
 				}
-				pos = world.tile_in_dir(machine.pos, machine.power_source?)?;
+				if machine.is_power_source() {
+					return true;
+				}
+				pos = match machine.power_source {
+					Some(pos) => pos,
+					None => return false,
+				};
 			}
-			None
+			false
 		})
 	}
 
 	#[allow(clippy::option_option)]
-	pub fn power_source_changed(&self, world: &World) -> Option<Option<Dir>> {
-		if self.is_spawn() {
+	pub fn power_source_changed(&self, world: &World) -> Option<Option<TilePos>> {
+		if self.is_power_source() {
 			return None;
 		}
 		let mut source = self.power_source;
 		if !source
-			.and_then(|dir| world.tile_in_dir(self.pos, dir))
 			.and_then(|p| world.machine_at(p))
-			.map(|m| m.has_power_source())
+			.map(|m| m.pos != self.pos && m.has_power_source())
 			.unwrap_or(false)
 		{
 			source = None
@@ -112,3 +636,79 @@ impl Machine {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rotate_offsets() {
+		// every Tile of a 2x2 footprint, in the order of the Tiles it moves to
+		let square = [(0, 0), (1, 0), (1, 1), (0, 1)];
+		for (i, offset) in square.iter().enumerate() {
+			for rotation in Dir::all() {
+				let expected = square[(i + rotation.num()) % 4];
+				assert_eq!(rotate_offset(*offset, (2, 2), rotation), expected);
+			}
+		}
+
+		let line = [
+			(Dir::Up, [(0, 0), (1, 0), (2, 0)]),
+			(Dir::Right, [(0, 0), (0, 1), (0, 2)]),
+			(Dir::Down, [(2, 0), (1, 0), (0, 0)]),
+			(Dir::Left, [(0, 2), (0, 1), (0, 0)]),
+		];
+		for (rotation, expected) in line.iter() {
+			for (x, tile) in expected.iter().enumerate() {
+				assert_eq!(rotate_offset((x, 0), (3, 1), *rotation), *tile);
+			}
+		}
+	}
+
+	#[test]
+	fn refinery_ports() {
+		use PortKind::*;
+		let pos = TilePos::new(10, 10);
+		let expected = [
+			(Dir::Up, [((0, 1), Dir::Left), ((1, 1), Dir::Right)]),
+			(Dir::Right, [((0, 0), Dir::Up), ((0, 1), Dir::Down)]),
+			(Dir::Down, [((1, 0), Dir::Right), ((0, 0), Dir::Left)]),
+			(Dir::Left, [((1, 1), Dir::Down), ((1, 0), Dir::Up)]),
+		];
+		for (rotation, [(input, input_side), (output, output_side)]) in expected.iter() {
+			let machine = Machine::new(pos, Refinery, *rotation);
+			let input = pos + TilePos::new(input.0, input.1);
+			let output = pos + TilePos::new(output.0, output.1);
+			assert_eq!(
+				machine.ports(),
+				vec![(input, *input_side, Input), (output, *output_side, Output)],
+				"facing {:?}",
+				rotation
+			);
+		}
+	}
+
+	#[test]
+	fn line_ports() {
+		let end = Port {
+			kind: PortKind::Output,
+			offset: (2, 0),
+			side: Dir::Right,
+		};
+		let start = Port {
+			kind: PortKind::Input,
+			offset: (0, 0),
+			side: Dir::Up,
+		};
+		let expected = [
+			(Dir::Up, ((2, 0), Dir::Right), ((0, 0), Dir::Up)),
+			(Dir::Right, ((0, 2), Dir::Down), ((0, 0), Dir::Right)),
+			(Dir::Down, ((0, 0), Dir::Left), ((2, 0), Dir::Down)),
+			(Dir::Left, ((0, 0), Dir::Up), ((0, 2), Dir::Left)),
+		];
+		for (rotation, end_port, start_port) in expected.iter() {
+			assert_eq!(rotate_port(&end, (3, 1), *rotation), *end_port);
+			assert_eq!(rotate_port(&start, (3, 1), *rotation), *start_port);
+		}
+	}
+}
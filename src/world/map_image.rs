@@ -0,0 +1,227 @@
+use super::{Grid, Layer, Material, Materials, TilePos};
+
+#[derive(Debug)]
+pub enum MapImageError {
+	Encoding(png::EncodingError),
+	Decoding(png::DecodingError),
+	UnknownColor {
+		pos: TilePos,
+		color: (u8, u8, u8),
+	},
+	/// An indexed PNG has room for 256 Palette entries
+	TooManyMaterials(usize),
+	/// The floor chunk names a Material that is not on the floor Layer
+	InvalidFloor {
+		pos: TilePos,
+		id: usize,
+	},
+}
+
+impl std::fmt::Display for MapImageError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			MapImageError::Encoding(e) => write!(f, "Unable to encode Map: {}", e),
+			MapImageError::Decoding(e) => write!(f, "Unable to decode Map: {}", e),
+			MapImageError::UnknownColor { pos, color } => {
+				write!(f, "Color {:?} at {} is not a Material", color, pos)
+			}
+			MapImageError::TooManyMaterials(count) => {
+				write!(f, "{} Materials don't fit into a Map image", count)
+			}
+			MapImageError::InvalidFloor { pos, id } => {
+				write!(f, "Floor {} at {} is not a floor Material", id, pos)
+			}
+		}
+	}
+}
+
+impl From<png::EncodingError> for MapImageError {
+	fn from(e: png::EncodingError) -> Self {
+		MapImageError::Encoding(e)
+	}
+}
+impl From<png::DecodingError> for MapImageError {
+	fn from(e: png::DecodingError) -> Self {
+		MapImageError::Decoding(e)
+	}
+}
+
+/// The private PNG chunk that holds the floor of every Tile, since the Pixels only show the top
+const FLOOR_CHUNK: [u8; 4] = *b"flOr";
+const PNG_SIGNATURE_LEN: usize = 8;
+
+/// Returns the data of the first chunk called `name` in the PNG `data`
+fn find_chunk(data: &[u8], name: [u8; 4]) -> Option<&[u8]> {
+	let mut rest = data.get(PNG_SIGNATURE_LEN..)?;
+	// every chunk is its length, its name, the data and a checksum
+	while rest.len() >= 12 {
+		let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+		let chunk = rest.get(8..8 + len)?;
+		if rest[4..8] == name {
+			return Some(chunk);
+		}
+		rest = rest.get(12 + len..)?;
+	}
+	None
+}
+
+impl Grid {
+	/// Encodes the Grid as an indexed PNG with one Pixel per Tile and one Palette entry per Material.
+	/// The floors below the structures are stored in an extra chunk
+	pub fn to_png(&self) -> Result<Vec<u8>, MapImageError> {
+		let TilePos {
+			x: width,
+			y: height,
+		} = self.size();
+
+		let materials = self.materials();
+		let count = materials.all().count();
+		if count > 256 {
+			return Err(MapImageError::TooManyMaterials(count));
+		}
+		let mut palette = Vec::with_capacity(materials.all().count() * 3);
+		for mat in materials.all() {
+			let (r, g, b) = materials[mat].color;
+			palette.push(r);
+			palette.push(g);
+			palette.push(b);
+		}
+
+		// Materials::all() is in registry order, so the ID is the Palette index
+		let mut data = Vec::with_capacity(width * height);
+		let mut floors = Vec::with_capacity(width * height);
+		for y in 0..height {
+			for x in 0..width {
+				data.push(self[(x, y)].id() as u8);
+				let floor = self.floor((x, y)).unwrap_or(Material::GROUND);
+				floors.push(floor.id() as u8);
+			}
+		}
+
+		let mut out = vec![];
+		{
+			let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+			encoder.set_color(png::ColorType::Indexed);
+			encoder.set_depth(png::BitDepth::Eight);
+			encoder.set_palette(palette);
+			let mut writer = encoder.write_header()?;
+			writer.write_image_data(&data)?;
+			writer.write_chunk(FLOOR_CHUNK, &floors)?;
+		}
+		Ok(out)
+	}
+
+	/// Creates a Grid from a PNG where every Pixel has the `color` of a Material in `materials`.
+	///
+	/// The Image does not have to be indexed, so that Maps can be drawn in any paint program.
+	/// Without the floor chunk of [`Grid::to_png`], every floor is `Ground`.
+	/// No Tiles are explored in the returned Grid.
+	pub fn from_png(data: &[u8], materials: Materials) -> Result<Grid, MapImageError> {
+		let mut decoder = png::Decoder::new(data);
+		decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+		let (info, mut reader) = decoder.read_info()?;
+
+		let mut buffer = vec![0; info.buffer_size()];
+		reader.next_frame(&mut buffer)?;
+
+		let samples = info.color_type.samples();
+		let (width, height) = (info.width as usize, info.height as usize);
+
+		let floors = find_chunk(data, FLOOR_CHUNK).filter(|f| f.len() == width * height);
+
+		let mut grid = Grid::filled(width, height, Material::BEDROCK, materials);
+		for y in 0..height {
+			let line = &buffer[y * info.line_size..];
+			for x in 0..width {
+				let pixel = &line[x * samples..];
+				let color = if samples < 3 {
+					// Grayscale (+ Alpha)
+					(pixel[0], pixel[0], pixel[0])
+				} else {
+					(pixel[0], pixel[1], pixel[2])
+				};
+				let pos = TilePos::new(x, y);
+				if let Some(floors) = floors {
+					let id = floors[y * width + x] as usize;
+					let floor = grid
+						.materials()
+						.all()
+						.nth(id)
+						.filter(|mat| grid.materials()[*mat].layer == Layer::Floor)
+						.ok_or(MapImageError::InvalidFloor { pos, id })?;
+					grid.set(pos, floor);
+				}
+				let mat = grid
+					.materials()
+					.from_color(color)
+					.ok_or(MapImageError::UnknownColor { pos, color })?;
+				grid.set(pos, mat);
+			}
+		}
+		Ok(grid)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn png_round_trip() {
+		let materials = Materials::load(
+			"[Gravel]\nlayer = floor\nsolid = false\nwalk_cost = 3\ncolor = 1, 2, 3\n",
+		);
+		let gravel = materials.get("Gravel").unwrap();
+		let mut grid = Grid::filled(5, 4, Material::ROCK, materials.clone());
+		grid.set((0, 0), Material::AIR);
+		grid.set((1, 0), Material::GRANITE);
+		grid.set((4, 1), Material::WATER);
+		grid.set((2, 3), Material::PLATFORM);
+		grid.set((3, 3), materials.get("Crystal").unwrap());
+		// a structure on a floor other than Ground, and the same floor uncovered
+		grid.set((1, 1), gravel);
+		grid.set((1, 1), Material::ROCK);
+		grid.set((2, 1), gravel);
+
+		let data = grid.to_png().unwrap();
+		let loaded = Grid::from_png(&data, materials).unwrap();
+
+		assert_eq!(loaded.size(), grid.size());
+		let size = grid.size();
+		for pos in TilePos::new(0, 0).rect_iter(TilePos::new(size.x - 1, size.y - 1)) {
+			assert_eq!(loaded.tile(pos), grid.tile(pos), "at {}", pos);
+		}
+	}
+
+	#[test]
+	fn unknown_color() {
+		let mut data = vec![];
+		{
+			let mut encoder = png::Encoder::new(&mut data, 2, 1);
+			encoder.set_color(png::ColorType::RGB);
+			encoder.set_depth(png::BitDepth::Eight);
+			let mut writer = encoder.write_header().unwrap();
+			writer.write_image_data(&[0, 0, 0, 1, 2, 3]).unwrap();
+		}
+		match Grid::from_png(&data, Materials::new()) {
+			Err(MapImageError::UnknownColor { pos, color }) => {
+				assert_eq!(pos, TilePos::new(1, 0));
+				assert_eq!(color, (1, 2, 3));
+			}
+			other => panic!("expected UnknownColor, got {:?}", other.map(|g| g.size())),
+		}
+	}
+
+	#[test]
+	fn too_many_materials() {
+		let text: String = (0..250)
+			.map(|i| format!("[Mat{}]\ncolor = {}, {}, 7\n", i, i, i / 2 + 1))
+			.collect();
+		let materials = Materials::load(&text);
+		let grid = Grid::filled(2, 2, Material::ROCK, materials);
+		match grid.to_png() {
+			Err(MapImageError::TooManyMaterials(count)) => assert!(count > 256),
+			other => panic!("expected TooManyMaterials, got {:?}", other.map(|_| ())),
+		}
+	}
+}
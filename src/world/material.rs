@@ -1,44 +1,398 @@
+use std::str::FromStr;
+
+/// Identifies a Material in the [`Materials`] registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Material(usize);
+
+impl Material {
+	pub const AIR: Material = Material(0);
+	pub const BEDROCK: Material = Material(1);
+	pub const GRANITE: Material = Material(2);
+	pub const ROCK: Material = Material(3);
+	pub const PLATFORM: Material = Material(4);
+	pub const MACHINE: Material = Material(5);
+	pub const GROUND: Material = Material(6);
+	pub const CONVEYOR: Material = Material(7);
+	pub const DEBRIS: Material = Material(8);
+	pub const WATER: Material = Material(9);
+	pub const LAVA: Material = Material(10);
+
+	/// The names of the Materials that the Game refers to directly, in the order of their IDs
+	const BUILTIN: [&'static str; 11] = [
+		"Air", "Bedrock", "Granite", "Rock", "Platform", "Machine", "Ground", "Conveyor", "Debris",
+		"Water", "Lava",
+	];
+
+	pub fn id(self) -> usize {
+		self.0
+	}
+}
+
+/// The part of a Tile that a Material occupies
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Material {
-	Air,
-	Bedrock,
-	Granite,
-	Rock,
-	Ore,
-	Crystal,
-	Debris,
-	Platform,
-	Machine,
+pub enum Layer {
+	/// The ground. Every Tile has a floor
+	Floor,
+	/// Walls, Debris, fluids, Platforms, Conveyors and Machines on top of the floor
+	Structure,
 }
 
-use Material::*;
+/// The properties of a Material
+#[derive(Debug, Clone)]
+pub struct MaterialInfo {
+	pub name: String,
+	/// The position in the asset atlas
+	pub sprite: (usize, usize),
+	pub layer: Layer,
+	pub solid: bool,
+	/// Ticks to walk across the Tile. Only used if the Material is not solid
+	pub walk_cost: usize,
+	/// Ticks to dig through the Tile. `None` if it can't be mined
+	pub hardness: Option<usize>,
+	/// The Minerals that drop when the Material is mined
+	pub mining_yield: Option<(Mineral, usize)>,
+	/// The Color in Map images and on the Minimap
+	pub color: (u8, u8, u8),
+	/// Generate veins of this Material in Rock, one every `min..max` Tiles
+	pub vein_spacing: Option<(usize, usize)>,
+	pub vein_growth: f64,
+	/// Holds up the ceiling, so that dug out Tiles nearby don't cave in. Defaults to `solid`
+	pub supports: Option<bool>,
+	/// Ticks between two steps of flowing. `None` if the Material is not a fluid
+	pub flow: Option<usize>,
+	/// The Color of the light that the Material gives off
+	pub glow: Option<(u8, u8, u8)>,
+}
 
-impl Material {
-	pub fn is_solid(self) -> bool {
-		const NOT_SOLID: u32 = (1 << Air as u32) | (1 << Platform as u32) | (1 << Debris as u32);
-		(NOT_SOLID & (1 << self as u32)) == 0
+impl MaterialInfo {
+	fn new(name: &str) -> Self {
+		Self {
+			name: name.to_string(),
+			sprite: (0, 0),
+			layer: Layer::Structure,
+			solid: true,
+			walk_cost: 1,
+			hardness: None,
+			mining_yield: None,
+			color: (0, 0, 0),
+			vein_spacing: None,
+			vein_growth: 0.0,
+			supports: None,
+			flow: None,
+			glow: None,
+		}
+	}
+
+	pub fn walk_cost(&self) -> Option<usize> {
+		if self.solid {
+			None
+		} else {
+			Some(self.walk_cost)
+		}
+	}
+	/// Walls that Workers can dig through
+	pub fn is_minable(&self) -> bool {
+		self.hardness.is_some()
+	}
+	pub fn is_fluid(&self) -> bool {
+		self.flow.is_some()
+	}
+	pub fn supports(&self) -> bool {
+		self.supports.unwrap_or(self.solid)
+	}
+
+	/// Sets the property `key` from the data file
+	fn set(&mut self, key: &str, value: &str) -> Option<()> {
+		match key {
+			"sprite" => match parse_list(value)?.as_slice() {
+				[row, col] => self.sprite = (*row, *col),
+				_ => return None,
+			},
+			"layer" => {
+				self.layer = match value {
+					"floor" => Layer::Floor,
+					"structure" => Layer::Structure,
+					_ => return None,
+				}
+			}
+			"solid" => self.solid = value.parse().ok()?,
+			"walk_cost" => self.walk_cost = value.parse().ok()?,
+			"hardness" => self.hardness = Some(value.parse().ok()?),
+			"yield" => {
+				let mut split = value.split_whitespace();
+				let count = split.next()?.parse().ok()?;
+				let mineral = Mineral::from_name(split.next()?)?;
+				self.mining_yield = Some((mineral, count));
+			}
+			"color" => match parse_list(value)?.as_slice() {
+				[r, g, b] => self.color = (*r, *g, *b),
+				_ => return None,
+			},
+			"glow" => match parse_list(value)?.as_slice() {
+				[r, g, b] => self.glow = Some((*r, *g, *b)),
+				_ => return None,
+			},
+			"vein_spacing" => match parse_list(value)?.as_slice() {
+				[min, max] if min < max => self.vein_spacing = Some((*min, *max)),
+				_ => return None,
+			},
+			"vein_growth" => self.vein_growth = value.parse().ok()?,
+			"supports" => self.supports = Some(value.parse().ok()?),
+			"flow" => match value.parse().ok()? {
+				0 => return None,
+				ticks => self.flow = Some(ticks),
+			},
+			_ => return None,
+		}
+		Some(())
+	}
+}
+
+fn parse_list<T: FromStr>(value: &str) -> Option<Vec<T>> {
+	value.split(',').map(|v| v.trim().parse().ok()).collect()
+}
+
+/// The registry of all Materials, loaded from a data file
+#[derive(Debug, Clone)]
+pub struct Materials {
+	materials: Vec<MaterialInfo>,
+}
+
+impl Materials {
+	pub const CONFIG_FILE: &'static str = "materials.cfg";
+	const DEFAULT_FILE: &'static str = "assets/materials.cfg";
+
+	/// The Materials that ship with the Game
+	pub fn new() -> Self {
+		let mut materials = Self { materials: vec![] };
+		let text = include_str!("../../assets/materials.cfg");
+		for error in materials.extend(text, Self::DEFAULT_FILE) {
+			crate::err!("{}", error);
+		}
+		for (i, name) in Material::BUILTIN.iter().enumerate() {
+			assert_eq!(
+				materials.materials.get(i).map(|m| m.name.as_str()),
+				Some(*name),
+				"{} has to start with the builtin Materials",
+				Self::DEFAULT_FILE
+			);
+		}
+		materials
+	}
+
+	/// Adds the Materials of a Mod or Scenario to the defaults. A section with the name of an
+	/// existing Material changes its properties
+	pub fn load(text: &str) -> Self {
+		let mut materials = Materials::new();
+		for error in materials.extend(text, Self::CONFIG_FILE) {
+			crate::err!("{}", error);
+		}
+		materials
+	}
+
+	/// Parses the sections of `text` into the registry. Returns the errors, which name the
+	/// line in `file` they were found in
+	fn extend(&mut self, text: &str, file: &str) -> Vec<String> {
+		let mut errors = vec![];
+		// the Material of the current section, the line of its header and if it has a color
+		let mut current: Option<(MaterialInfo, usize, bool)> = None;
+		for (i, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			if line.starts_with('[') && line.ends_with(']') {
+				if let Some(section) = current.take() {
+					self.finish(section, file, &mut errors);
+				}
+				let name = line[1..line.len() - 1].trim();
+				current = Some(match self.get(name) {
+					Some(mat) => (self[mat].clone(), i + 1, true),
+					None => (MaterialInfo::new(name), i + 1, false),
+				});
+				continue;
+			}
+			let (info, _, has_color) = match current.as_mut() {
+				Some(section) => section,
+				None => {
+					errors.push(format!("{} line {}: expected `[Name]`", file, i + 1));
+					continue;
+				}
+			};
+			let mut split = line.splitn(2, '=').map(str::trim);
+			let valid = match (split.next(), split.next()) {
+				(Some(key), Some(value)) => {
+					let valid = info.set(key, value).is_some();
+					*has_color |= valid && key == "color";
+					valid
+				}
+				_ => false,
+			};
+			if !valid {
+				let error = format!("{} line {}: invalid property `{}`", file, i + 1, line);
+				errors.push(error);
+			}
+		}
+		if let Some(section) = current {
+			self.finish(section, file, &mut errors);
+		}
+		errors
 	}
-	pub fn walk_cost(self) -> Option<usize> {
-		match self {
-			Air => Some(2),
-			Platform => Some(1),
-			Debris => Some(4),
-			_ => None, // solid
+
+	/// Adds a parsed section to the registry. Every Material needs its own color, so that
+	/// Map images can tell them apart
+	fn finish(
+		&mut self,
+		(info, line, has_color): (MaterialInfo, usize, bool),
+		file: &str,
+		errors: &mut Vec<String>,
+	) {
+		let same_color = self
+			.all()
+			.find(|mat| self[*mat].color == info.color && self[*mat].name != info.name);
+		if !has_color {
+			errors.push(format!(
+				"{} line {}: `{}` has no color",
+				file, line, info.name
+			));
+		} else if let Some(other) = same_color {
+			errors.push(format!(
+				"{} line {}: `{}` has the same color as `{}`",
+				file, line, info.name, self[other].name
+			));
+		}
+		self.insert(info);
+	}
+
+	fn insert(&mut self, info: MaterialInfo) {
+		match self.get(&info.name) {
+			Some(mat) => self.materials[mat.0] = info,
+			None => self.materials.push(info),
 		}
 	}
+
+	pub fn all(&self) -> impl DoubleEndedIterator<Item = Material> {
+		(0..self.materials.len()).map(Material)
+	}
+	pub fn get(&self, name: &str) -> Option<Material> {
+		self.all().find(|mat| self[*mat].name == name)
+	}
+	pub fn from_color(&self, color: (u8, u8, u8)) -> Option<Material> {
+		self.all().find(|mat| self[*mat].color == color)
+	}
+}
+
+impl std::ops::Index<Material> for Materials {
+	type Output = MaterialInfo;
+	fn index(&self, mat: Material) -> &MaterialInfo {
+		&self.materials[mat.0]
+	}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mineral {
 	Ore,
 	Crystal,
+	/// Refined from Ore in a Refinery
+	Metal,
 }
 
 impl Mineral {
 	pub fn count() -> usize {
-		2
+		3
+	}
+	pub fn all() -> impl DoubleEndedIterator<Item = Mineral> {
+		[Mineral::Ore, Mineral::Crystal, Mineral::Metal]
+			.iter()
+			.copied()
 	}
 	pub fn num(self) -> usize {
 		self as usize
 	}
+	pub fn from_name(name: &str) -> Option<Mineral> {
+		Mineral::all().find(|m| format!("{:?}", m) == name)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(text: &str) -> (Materials, Vec<String>) {
+		let mut materials = Materials::new();
+		let errors = materials.extend(text, "test.cfg");
+		(materials, errors)
+	}
+
+	#[test]
+	fn default_file_is_valid() {
+		let mut materials = Materials { materials: vec![] };
+		let errors = materials.extend(include_str!("../../assets/materials.cfg"), "default");
+		assert_eq!(errors, Vec::<String>::new());
+		assert_eq!(materials.get("Ground"), Some(Material::GROUND));
+		assert_eq!(materials.get("Lava"), Some(Material::LAVA));
+	}
+
+	#[test]
+	fn new_material() {
+		let (materials, errors) = parse(
+			"[Gold]\nsprite = 2, 5\nhardness = 4\nyield = 2 Ore\ncolor = 250, 200, 0\nglow = 1, 2, 3",
+		);
+		assert!(errors.is_empty(), "{:?}", errors);
+		let gold = materials.get("Gold").expect("Gold was not added");
+		let info = &materials[gold];
+		assert_eq!(info.sprite, (2, 5));
+		assert_eq!(info.layer, Layer::Structure);
+		assert!(info.solid && info.is_minable() && info.supports());
+		assert_eq!(info.hardness, Some(4));
+		assert_eq!(info.mining_yield, Some((Mineral::Ore, 2)));
+		assert_eq!(info.glow, Some((1, 2, 3)));
+		assert_eq!(materials.from_color((250, 200, 0)), Some(gold));
+	}
+
+	#[test]
+	fn changes_existing_material() {
+		let before = Materials::new().all().count();
+		let (materials, errors) = parse("[Rock]\nhardness = 5");
+		assert!(errors.is_empty(), "{:?}", errors);
+		assert_eq!(materials.all().count(), before);
+		let rock = &materials[Material::ROCK];
+		assert_eq!(rock.hardness, Some(5));
+		assert_eq!(rock.color, Materials::new()[Material::ROCK].color);
+	}
+
+	#[test]
+	fn invalid_properties() {
+		let (materials, errors) = parse(
+			"hardness = 1\n[Gold]\ncolor = 1, 2\nflow = 0\nyield = 1 Gold\nshiny\ncolor = 1, 2, 3",
+		);
+		assert_eq!(
+			errors,
+			vec![
+				"test.cfg line 1: expected `[Name]`",
+				"test.cfg line 3: invalid property `color = 1, 2`",
+				"test.cfg line 4: invalid property `flow = 0`",
+				"test.cfg line 5: invalid property `yield = 1 Gold`",
+				"test.cfg line 6: invalid property `shiny`",
+			]
+		);
+		let gold = materials.get("Gold").expect("Gold was not added");
+		assert_eq!(materials[gold].flow, None);
+		assert_eq!(materials[gold].color, (1, 2, 3));
+	}
+
+	#[test]
+	fn missing_color() {
+		let (_, errors) = parse("# comment\n\n[Gold]\nhardness = 1");
+		assert_eq!(errors, vec!["test.cfg line 3: `Gold` has no color"]);
+	}
+
+	#[test]
+	fn duplicate_color() {
+		let (_, errors) = parse("[Gold]\ncolor = 0, 0, 0\n[Silver]\ncolor = 9, 9, 9");
+		assert_eq!(
+			errors,
+			vec!["test.cfg line 1: `Gold` has the same color as `Air`"]
+		);
+	}
 }
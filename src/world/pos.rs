@@ -62,21 +62,28 @@ impl TilePos {
 			tl,
 			br,
 			current: tl,
+			done: false,
 		}
 	}
 }
+/// Iterates over all Tiles in a Rectangle, including both corners
 pub struct RectIter {
 	tl: TilePos,
 	br: TilePos,
 	current: TilePos,
+	done: bool,
 }
 impl Iterator for RectIter {
 	type Item = TilePos;
 	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
 		let ret = Some(self.current);
 		if self.current.x == self.br.x {
 			if self.current.y == self.br.y {
-				return None;
+				self.done = true;
+				return ret;
 			}
 			self.current.x = self.tl.x;
 			self.current.y += 1;
@@ -208,3 +215,36 @@ impl fmt::Display for TilePos {
 		write!(f, "T({}, {})", self.x, self.y)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rect_iter_order() {
+		let tiles: Vec<TilePos> = TilePos::new(1, 2).rect_iter(TilePos::new(2, 3)).collect();
+		let expected = [(1, 2), (2, 2), (1, 3), (2, 3)];
+		assert_eq!(
+			tiles,
+			expected
+				.iter()
+				.map(|&(x, y)| TilePos::new(x, y))
+				.collect::<Vec<_>>()
+		);
+
+		let single: Vec<TilePos> = TilePos::new(4, 4).rect_iter(TilePos::new(4, 4)).collect();
+		assert_eq!(single, vec![TilePos::new(4, 4)]);
+	}
+
+	#[test]
+	fn rect_iter_reversed_corners() {
+		let (tl, br) = (TilePos::new(1, 2), TilePos::new(3, 5));
+		let forward: Vec<TilePos> = tl.rect_iter(br).collect();
+		assert_eq!(forward.len(), 3 * 4);
+		// dragging from any corner covers the same Tiles in the same order
+		assert_eq!(br.rect_iter(tl).collect::<Vec<_>>(), forward);
+		let (tr, bl) = (TilePos::new(3, 2), TilePos::new(1, 5));
+		assert_eq!(tr.rect_iter(bl).collect::<Vec<_>>(), forward);
+		assert_eq!(bl.rect_iter(tr).collect::<Vec<_>>(), forward);
+	}
+}
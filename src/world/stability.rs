@@ -0,0 +1,156 @@
+use super::{Grid, TilePos};
+use crate::{HashMap, HashSet};
+use rand::Rng;
+
+/// Finds dug out areas that are too wide to hold up their ceiling and lets them cave in
+pub struct Stability {
+	/// Tiles that Workers dug out. Natural caves never collapse
+	excavated: HashSet<TilePos>,
+	/// The Ticks that each unsupported Tile has been at risk for
+	stress: HashMap<TilePos, usize>,
+	/// The supports changed since the last update
+	dirty: bool,
+}
+
+impl Stability {
+	/// How many Tiles a dug out Tile can be away from the nearest support
+	pub const MAX_SPAN: usize = 3;
+	/// The Ticks before an unsupported Tile can cave in
+	const GRACE_TICKS: usize = 150;
+	/// The chance per Tick that a Tile caves in once its grace period is over
+	const COLLAPSE_CHANCE: f64 = 0.05;
+
+	pub fn new() -> Self {
+		Self {
+			excavated: HashSet::default(),
+			stress: HashMap::default(),
+			dirty: false,
+		}
+	}
+
+	pub fn excavate(&mut self, pos: TilePos) {
+		self.excavated.insert(pos);
+		self.dirty = true;
+	}
+	pub fn set_dirty(&mut self) {
+		self.dirty = true;
+	}
+
+	pub fn is_at_risk(&self, pos: TilePos) -> bool {
+		self.stress.contains_key(&pos)
+	}
+	pub fn at_risk(&self) -> impl Iterator<Item = TilePos> + '_ {
+		self.stress.keys().copied()
+	}
+
+	/// Returns the dug out Tiles that are too far from any support, as if the Tiles in
+	/// `removed` were dug out as well
+	pub fn unsupported(&self, grid: &Grid, removed: &HashSet<TilePos>) -> Vec<TilePos> {
+		let span = Self::MAX_SPAN as isize;
+		let supports = |x: isize, y: isize| {
+			if x < 0 || y < 0 {
+				return true;
+			}
+			let pos = TilePos::new(x as usize, y as usize);
+			!removed.contains(&pos) && grid.supports(pos)
+		};
+		self.excavated
+			.union(removed)
+			.copied()
+			.filter(|pos| {
+				let (x, y) = (pos.x as isize, pos.y as isize);
+				if supports(x, y) {
+					return false;
+				}
+				!(y - span..=y + span).any(|sy| (x - span..=x + span).any(|sx| supports(sx, sy)))
+			})
+			.collect()
+	}
+
+	/// Adds stress to the unsupported Tiles. Returns the Tiles that cave in
+	pub fn update(&mut self, grid: &Grid) -> Vec<TilePos> {
+		if self.dirty {
+			self.dirty = false;
+			let unsupported: HashSet<TilePos> = self
+				.unsupported(grid, &HashSet::default())
+				.into_iter()
+				.collect();
+			self.stress.retain(|pos, _| unsupported.contains(pos));
+			for pos in unsupported {
+				self.stress.entry(pos).or_insert(0);
+			}
+		}
+
+		let mut rng = rand::thread_rng();
+		let mut collapsed = vec![];
+		for (pos, stress) in self.stress.iter_mut() {
+			*stress += 1;
+			if *stress > Self::GRACE_TICKS && rng.gen_bool(Self::COLLAPSE_CHANCE) {
+				collapsed.push(*pos);
+			}
+		}
+		for pos in collapsed.iter() {
+			self.stress.remove(pos);
+		}
+		collapsed
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::world::{Material, Materials};
+
+	/// An open cave with a single Rock pillar in the middle
+	fn cave() -> Grid {
+		let mut grid = Grid::filled(30, 30, Material::AIR, Materials::new());
+		grid.set((15, 15), Material::ROCK);
+		grid
+	}
+
+	fn sorted(mut tiles: Vec<TilePos>) -> Vec<(usize, usize)> {
+		tiles.sort_by_key(|pos| (pos.x, pos.y));
+		tiles.into_iter().map(|pos| (pos.x, pos.y)).collect()
+	}
+
+	#[test]
+	fn max_span() {
+		let grid = cave();
+		let mut stability = Stability::new();
+		let span = Stability::MAX_SPAN;
+		stability.excavate(TilePos::new(15 + span, 15));
+		stability.excavate(TilePos::new(15 + span + 1, 15));
+		stability.excavate(TilePos::new(15 - span, 15 - span));
+
+		let unsupported = stability.unsupported(&grid, &HashSet::default());
+		assert_eq!(sorted(unsupported), vec![(15 + span + 1, 15)]);
+	}
+
+	#[test]
+	fn world_edge_supports() {
+		let grid = cave();
+		let mut stability = Stability::new();
+		let span = Stability::MAX_SPAN;
+		stability.excavate(TilePos::new(span - 1, 5));
+		stability.excavate(TilePos::new(span, 5));
+		stability.excavate(TilePos::new(30 - span, 5));
+		stability.excavate(TilePos::new(29 - span, 5));
+
+		let unsupported = stability.unsupported(&grid, &HashSet::default());
+		assert_eq!(sorted(unsupported), vec![(span, 5), (29 - span, 5)]);
+	}
+
+	#[test]
+	fn removed_preview() {
+		let grid = cave();
+		let mut stability = Stability::new();
+		stability.excavate(TilePos::new(17, 15));
+		assert!(stability.unsupported(&grid, &HashSet::default()).is_empty());
+
+		let removed = std::iter::once(TilePos::new(15, 15)).collect();
+		let unsupported = stability.unsupported(&grid, &removed);
+		assert_eq!(sorted(unsupported), vec![(15, 15), (17, 15)]);
+		// the preview doesn't change anything
+		assert!(stability.unsupported(&grid, &HashSet::default()).is_empty());
+	}
+}
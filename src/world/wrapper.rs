@@ -1,5 +1,11 @@
-use super::{Dir, Grid, Machine, MachineType, Material, TilePos};
-use crate::{HashMap, HashSet};
+use super::{
+	Dir, Fluids, Grid, Layer, Light, Lighting, Machine, MachineAction, MachineType, Material,
+	Materials, Mineral, Power, Product, Stability, Tile, TilePos,
+};
+use crate::{
+	ui::{KeyCode, MenuItem},
+	Command, HashMap, HashSet,
+};
 use hierarchical_pathfinding::{
 	prelude::{ManhattanNeighborhood, PathCache, PathCacheConfig},
 	AbstractPath,
@@ -13,14 +19,29 @@ pub struct World {
 	hpa_map: PathCache<Neighborhood>,
 	dirty: bool,
 	changes: HashSet<TilePos>,
+	/// Tiles that look different since the last call to `take_visual_changes`
+	visual_changes: HashSet<TilePos>,
 	machines: HashMap<TilePos, Machine>,
+	/// The `pos` of the Machine that covers a Tile
+	machine_tiles: HashMap<TilePos, TilePos>,
 	spawns: HashSet<TilePos>,
+	/// Minerals from the storage of removed Machines that still have to be dropped as Items
+	spilled: Vec<(TilePos, Mineral)>,
+	/// Minerals that were spent or refunded since the last call to `take_stock_changes`
+	stock_changes: Vec<(Mineral, isize)>,
+	stability: Stability,
+	fluids: Fluids,
+	lighting: Lighting,
 	debug_mode: bool,
+	/// Off when a Game starts, so that Commands create Jobs for the Workers
+	editor_mode: bool,
 }
 
 impl World {
-	pub fn new(width: usize, height: usize) -> Self {
-		let grid = Grid::new(width, height);
+	pub fn new(width: usize, height: usize, materials: Materials) -> Self {
+		let grid = Grid::new(width, height, materials);
+		let fluids = Fluids::new(&grid);
+		let lighting = Lighting::new(&grid);
 
 		let neighborhood = Neighborhood::new(width, height);
 		let hpa_map = PathCache::new(
@@ -37,9 +58,17 @@ impl World {
 			hpa_map,
 			dirty: true,
 			changes: HashSet::default(),
+			visual_changes: HashSet::default(),
 			machines: HashMap::default(),
+			machine_tiles: HashMap::default(),
 			spawns: HashSet::default(),
+			spilled: vec![],
+			stock_changes: vec![],
+			stability: Stability::new(),
+			fluids,
+			lighting,
 			debug_mode: false,
+			editor_mode: false,
 		}
 	}
 
@@ -59,121 +88,291 @@ impl World {
 		self.set_dirty();
 	}
 
+	/// In editor mode, Commands change the World directly instead of creating Jobs
+	pub fn editor_mode(&self) -> bool {
+		self.editor_mode
+	}
+	pub fn toggle_editor_mode(&mut self) {
+		self.editor_mode = !self.editor_mode;
+	}
+
+	/// Puts `mat` on its Layer at `pos`. Setting `Air` removes the structure and uncovers the floor
 	pub fn set<T: Into<TilePos>>(&mut self, pos: T, mat: Material) {
+		let pos: TilePos = pos.into();
+		let mut tile = self
+			.grid
+			.tile(pos)
+			.unwrap_or_else(|| panic!("Called set on invalid pos: {}", pos));
+		tile.place(mat, self.grid.materials());
+		self.set_tile(pos, tile);
+	}
+	pub fn set_tile<T: Into<TilePos>>(&mut self, pos: T, tile: Tile) {
 		let pos: TilePos = pos.into();
 		self.set_dirty();
 		self.changes.insert(pos);
+		self.visual_changes.insert(pos);
 		let old = self
 			.grid
-			.get(pos)
+			.structure(pos)
+			.or_else(|| self.grid.floor(pos))
 			.unwrap_or_else(|| panic!("Called set on invalid pos: {}", pos));
 
-		use Material::*;
 		match old {
-			Machine | Platform => {
-				self.machines
-					.remove(&pos)
-					.expect("Missing Machine")
-					.remove();
-			}
+			Material::MACHINE | Material::PLATFORM | Material::CONVEYOR => self.remove_machine(pos),
 			_ => {}
 		}
 
-		self.grid[pos] = mat;
+		let was_wall = self.materials()[old].is_minable();
+		let was_solid = self.grid.is_solid(pos);
+		self.grid.set_tile(pos, tile);
+		if was_wall && !self.grid.is_solid(pos) {
+			self.stability.excavate(pos);
+		} else {
+			self.stability.set_dirty();
+		}
+		self.fluids.tile_changed(pos, &self.grid);
+		self.lighting.tile_changed(pos, was_solid, &self.grid);
 
-		if mat == Material::Platform {
+		if tile.structure == Some(Material::PLATFORM) {
+			self.machine_tiles.insert(pos, pos);
 			self.machines
-				.insert(pos, super::Machine::new(pos, MachineType::Platform));
+				.insert(pos, Machine::new(pos, MachineType::Platform, Dir::Up));
 		}
 	}
+	/// Removes the Machine covering `pos` and clears the rest of its footprint
+	fn remove_machine(&mut self, pos: TilePos) {
+		let origin = *self.machine_tiles.get(&pos).expect("Missing Machine");
+		let mut machine = self.machines.remove(&origin).expect("Missing Machine");
+		self.spawns.remove(&origin);
+		let tiles: Vec<TilePos> = machine.tiles().collect();
+		let contents = machine
+			.take_storage()
+			.into_iter()
+			.flat_map(|(mineral, amount)| (0..amount).map(move |_| mineral));
+		for (i, mineral) in contents.enumerate() {
+			self.spilled.push((tiles[i % tiles.len()], mineral));
+		}
+		for tile in machine.tiles() {
+			self.machine_tiles.remove(&tile);
+			if tile != pos {
+				let mut rest = self.grid.tile(tile).expect("Machine outside of the World");
+				rest.structure = None;
+				let was_solid = self.grid.is_solid(tile);
+				// the same updates as in set_tile, which can't be used while the Machine is half removed
+				self.grid.set_tile(tile, rest);
+				self.stability.set_dirty();
+				self.fluids.tile_changed(tile, &self.grid);
+				self.lighting.tile_changed(tile, was_solid, &self.grid);
+				self.changes.insert(tile);
+				self.visual_changes.insert(tile);
+			}
+		}
+		machine.remove();
+	}
+	pub fn tile(&self, pos: TilePos) -> Option<Tile> {
+		self.grid.tile(pos)
+	}
+	pub fn materials(&self) -> &Materials {
+		self.grid.materials()
+	}
+	/// Checks if the Tile at `pos` has a floor that can be built on
+	pub fn is_empty(&self, pos: TilePos) -> bool {
+		self.tile(pos)
+			.map(|tile| tile.structure.is_none())
+			.unwrap_or(false)
+	}
+	/// Walls that can be dug out and Debris or Platforms that can be cleared away
+	pub fn is_removable(&self, pos: TilePos) -> bool {
+		let info = &self.materials()[self[pos]];
+		info.layer == Layer::Structure && !info.is_fluid() && (info.is_minable() || !info.solid)
+	}
 
-	pub fn set_visible_p(&mut self, pos: TilePos) {
-		self.set_dirty();
-		self.grid.set_visible(pos)
+	/// Updates what the Player can see. `eyes` are the positions and sight radii of the
+	/// Workers, Machines add their own
+	pub fn update_vision(&mut self, mut eyes: Vec<(TilePos, usize)>) {
+		for machine in self.machines.values() {
+			if !machine.machine_type.is_dragged() {
+				eyes.extend(machine.tiles().map(|tile| (tile, Machine::SIGHT)));
+			}
+		}
+		let explored = self.grid.update_vision(&eyes);
+		if !explored.is_empty() {
+			self.set_dirty();
+			self.visual_changes.extend(explored);
+		}
+	}
+
+	/// How far glowing Materials light up their surroundings
+	const GLOW_RADIUS: usize = 2;
+	/// Updates the light map. `lights` are the lamps of the Workers, powered Machines and
+	/// explored glowing Tiles add their own
+	pub fn update_lighting(&mut self, mut lights: Vec<Light>) {
+		for machine in self.machines.values() {
+			if machine.power() && machine.machine_type.info().power != Power::Unused {
+				let light = |tile| (tile, Machine::LIGHT_RADIUS, Machine::LIGHT_COLOR);
+				lights.extend(machine.tiles().map(light));
+			}
+		}
+		for (pos, color) in self.lighting.glows() {
+			if self.grid.is_explored(pos) {
+				lights.push((pos, Self::GLOW_RADIUS, color));
+			}
+		}
+		self.lighting.update(&self.grid, lights);
 	}
 
 	const AIR_PLACE_PLATFORM: usize = 0;
-	const SPAWN_NEW_WORKER: usize = 0;
+	const AIR_PLACE_LAB: usize = 1;
 	const TILE_REMOVE: usize = 99;
-	pub fn context_menu_tile(
-		&self,
-		tile: TilePos,
-	) -> Box<dyn Iterator<Item = &(usize, &'static str)>> {
-		use Material::*;
+	pub fn context_menu_tile(&self, tile: TilePos) -> Vec<MenuItem> {
+		let remove = |text| MenuItem::new(Self::TILE_REMOVE, text).hotkey(KeyCode::Delete);
 		match self[tile] {
-			Air => Box::new([(Self::AIR_PLACE_PLATFORM, "Place Platform")].iter()),
-			Bedrock => panic!("Bedrock was selected"),
-			Granite | Rock | Ore | Crystal => Box::new([(Self::TILE_REMOVE, "Remove")].iter()),
-			Debris => Box::new([(Self::TILE_REMOVE, "Remove")].iter()),
-			Platform => Box::new([(Self::TILE_REMOVE, "Remove")].iter()),
-			Machine => match self.machine_at(tile).unwrap().machine_type {
-				MachineType::Spawn => Box::new(
-					[
-						(Self::SPAWN_NEW_WORKER, "Spawn Worker"),
-						(Self::TILE_REMOVE, "Destroy"),
-					]
-					.iter(),
-				),
-				MachineType::Lab => {
-					Box::new([(0, "TODO: Research"), (Self::TILE_REMOVE, "Destroy")].iter())
+			_ if self.is_empty(tile) => vec![MenuItem::submenu(
+				"Build",
+				vec![
+					MenuItem::new(Self::AIR_PLACE_PLATFORM, "Platform")
+						.icon((2, 0))
+						.hotkey(KeyCode::Letter('p')),
+					MenuItem::new(Self::AIR_PLACE_LAB, "Lab")
+						.icon(MachineType::Lab.icon())
+						.hotkey(KeyCode::Letter('l'))
+						.disabled_if(!self.is_powered_nearby(tile), "no Power nearby"),
+				],
+			)],
+			Material::MACHINE => {
+				let machine = match self.machine_at(tile) {
+					Some(machine) => machine,
+					None => return vec![],
+				};
+				let info = machine.machine_type.info();
+				// the index of the action is the id of the MenuItem
+				let mut items: Vec<MenuItem> = info
+					.actions
+					.iter()
+					.enumerate()
+					.map(|(i, (action, label))| {
+						let item = MenuItem::new(i, *label);
+						match action {
+							MachineAction::Produce => {
+								item.disabled_if(machine.cycle_progress().is_some(), "busy")
+							}
+							MachineAction::Research => item.disabled("not implemented"),
+						}
+					})
+					.collect();
+				if info.actions.is_empty() {
+					items.push(remove("Remove"));
+				} else {
+					items.push(remove("Destroy"));
 				}
-				_ => Box::new([(Self::TILE_REMOVE, "Remove")].iter()),
-			},
+				items
+			}
+			_ if self.is_removable(tile) => {
+				let tiles = std::iter::once(tile).collect();
+				vec![remove(&self.remove_label(&tiles))]
+			}
+			_ => vec![],
 		}
 	}
-	pub fn context_menu_walls(
-		&self,
-		_tiles: &HashSet<TilePos>,
-	) -> Box<dyn Iterator<Item = &(usize, &'static str)>> {
-		Box::new([(Self::TILE_REMOVE, "Remove")].iter())
+	pub fn context_menu_walls(&self, tiles: &HashSet<TilePos>) -> Vec<MenuItem> {
+		vec![MenuItem::new(Self::TILE_REMOVE, self.remove_label(tiles)).hotkey(KeyCode::Delete)]
 	}
-
-	pub fn context_click_tile(&mut self, tile: TilePos, id: usize) -> bool {
-		if id == Self::TILE_REMOVE {
-			self.set(tile, Air);
-			return true;
+	/// Warns about the Tiles that would be at risk of caving in after removing `tiles`
+	fn remove_label(&self, tiles: &HashSet<TilePos>) -> String {
+		match self.cave_in_risk(tiles) {
+			0 => String::from("Remove"),
+			n => format!("Remove ({} Tiles at risk of caving in)", n),
 		}
+	}
 
-		use Material::*;
-		match self[tile] {
-			Air => match id {
-				Self::AIR_PLACE_PLATFORM => {
-					self.add_machine(
-						tile,
-						MachineType::ConstructionSite(Box::new(MachineType::Platform)),
-					);
-					true
+	pub fn context_click_tile(&self, tile: TilePos, id: usize) -> Option<Command> {
+		let command = match self[tile] {
+			_ if self.is_empty(tile) => match id {
+				Self::AIR_PLACE_PLATFORM => Some(Command::PlacePlatform(tile)),
+				Self::AIR_PLACE_LAB if self.is_powered_nearby(tile) => {
+					Some(Command::PlaceMachine(tile, MachineType::Lab, Dir::Up))
 				}
-				x => panic!("Invalid Context Menu Item on Air: {}", x),
+				_ => None,
 			},
-			Bedrock => panic!("Bedrock was selected"),
-			Machine => match &self.machine_at(tile).unwrap().machine_type {
-				MachineType::Spawn => match id {
-					Self::SPAWN_NEW_WORKER => false, // TODO: spawn Worker
-					x => panic!("Invalid Context Menu Item on Spawn: {}", x),
-				},
-				MachineType::Lab => false, // TODO: Research
-				m => panic!("Invalid Context Menu Item on {:?}: {}", m, id),
-			},
-			m => panic!("Invalid Context Menu Item on {:?}: {}", m, id),
+			Material::MACHINE if id == Self::TILE_REMOVE => Some(Command::DestroyMachine(tile)),
+			Material::MACHINE => {
+				let actions = self.machine_at(tile).map(|m| m.machine_type.info().actions);
+				match actions.and_then(|actions| actions.get(id)) {
+					Some((MachineAction::Produce, _)) => Some(Command::StartProduction(tile)),
+					_ => None, // TODO: Research
+				}
+			}
+			_ if id == Self::TILE_REMOVE && self.is_removable(tile) => {
+				Some(Command::RemoveTiles(vec![tile]))
+			}
+			_ => None,
+		};
+		if command.is_none() {
+			crate::err!("Invalid Context Menu Item on {}: {}", tile, id);
 		}
+		command
 	}
-	pub fn context_click_walls(&mut self, tiles: &HashSet<TilePos>, id: usize) -> bool {
+	pub fn context_click_walls(&self, tiles: &HashSet<TilePos>, id: usize) -> Option<Command> {
 		match id {
-			Self::TILE_REMOVE => {
-				for tile in tiles {
-					self.set(*tile, Material::Air);
-				}
-				true
+			Self::TILE_REMOVE => Some(Command::RemoveTiles(tiles.iter().copied().collect())),
+			x => {
+				crate::err!("Invalid Context Menu Item on Walls: {}", x);
+				None
 			}
-			x => panic!("Invalid Context Menu Item on Walls: {}", x),
 		}
 	}
 
+	/// Counts the Tiles that removing `tiles` would newly put at risk of caving in
+	pub fn cave_in_risk(&self, tiles: &HashSet<TilePos>) -> usize {
+		let walls = tiles
+			.iter()
+			.copied()
+			.filter(|tile| self.grid.supports(*tile))
+			.collect();
+		self.stability
+			.unsupported(&self.grid, &walls)
+			.into_iter()
+			.filter(|tile| !self.stability.is_at_risk(*tile))
+			.count()
+	}
+	pub fn is_at_risk(&self, pos: TilePos) -> bool {
+		self.stability.is_at_risk(pos)
+	}
+
+	/// How much fluid is on the Tile at `pos`
+	pub fn fluid_level(&self, pos: TilePos) -> usize {
+		self.fluids.level(pos)
+	}
+
+	/// Lets Water and Lava flow. Returns the Tiles that changed and their new Material
+	pub fn update_fluids(&mut self) -> Vec<(TilePos, Material)> {
+		let changes = self.fluids.update(&self.grid);
+		for (tile, mat) in changes.iter() {
+			self.set(*tile, *mat);
+		}
+		changes
+	}
+
+	/// Lets unsupported Tiles cave in and fills them with Debris. Returns the Tiles that collapsed
+	pub fn update_stability(&mut self) -> Vec<TilePos> {
+		let collapsed = self.stability.update(&self.grid);
+		for tile in collapsed.iter() {
+			self.set(*tile, Material::DEBRIS);
+		}
+		collapsed
+	}
+
+	/// Checks if a Machine next to `tile` is connected to a Spawn
+	pub fn is_powered_nearby(&self, tile: TilePos) -> bool {
+		self.neighbors_of(tile)
+			.filter_map(|p| self.machine_at(p))
+			.any(|m| m.has_power_source())
+	}
+
 	pub fn draw(&mut self, backend: &mut crate::Backend) {
 		use crate::{BackendStyle, Colors};
-		use Material::{Machine, Platform};
 
+		let relit = self.lighting.take_changes();
 		if self.dirty {
 			self.dirty = false;
 
@@ -181,31 +380,51 @@ impl World {
 
 			for y in 0..self.height() {
 				for x in 0..self.width() {
-					if self.debug_mode || self.grid.is_visible((x, y)) {
-						let mat = self.grid[(x, y)];
-						let pos = super::TilePos::new(x, y);
-						let (row, col) = if mat == Platform {
-							let variant = Dir::all()
-								.map(|dir| {
-									self.grid
-										.tile_in_dir(pos, dir)
-										.and_then(|p| self.grid.get(p))
-										.map(|mat| mat == Platform || mat == Machine)
-										.unwrap_or(false) as usize
-								})
-								.rfold(0, |prev, cur| (prev << 1) | cur);
-							(2, variant)
-						} else {
-							(0, mat as usize)
-						};
-						backend.draw_to_background((row, col), pos)
+					if self.debug_mode || self.grid.is_explored((x, y)) {
+						self.draw_tile(backend, TilePos::new(x, y));
 					}
 				}
 			}
+		} else {
+			for pos in relit {
+				if self.debug_mode || self.grid.is_explored(pos) {
+					self.draw_tile(backend, pos);
+				}
+			}
 		}
 
 		backend.draw_background();
 
+		// dim the explored Tiles that are out of sight, one rectangle per run in a row
+		if !self.debug_mode {
+			for y in 0..self.height() {
+				let mut run_start = None;
+				for x in 0..=self.width() {
+					let dim = x < self.width()
+						&& self.grid.visibility((x, y)) == super::Visibility::Explored;
+					match (dim, run_start) {
+						(true, None) => run_start = Some(x),
+						(false, Some(start)) => {
+							let size = super::GamePos::new((x - start) as f32, 1.0);
+							backend.fill_rect(
+								super::TilePos::new(start, y),
+								size * super::TILE_SIZE as f32,
+								Colors::Fog,
+							);
+							run_start = None;
+						}
+						_ => {}
+					}
+				}
+			}
+		}
+
+		for tile in self.stability.at_risk() {
+			if self.debug_mode || self.grid.is_explored(tile) {
+				backend.fill_rect(tile, super::GamePos::TILE, Colors::CaveIn);
+			}
+		}
+
 		for machine in self.machines.values() {
 			machine.draw(backend);
 		}
@@ -253,7 +472,75 @@ impl World {
 		// ============================= </Node Drawing> =============================
 	}
 
-	pub fn update(&mut self, spawn_has_power: bool) {
+	/// Draws the Tile at `pos` to the background cache, darkened by the light map
+	fn draw_tile(&self, backend: &mut crate::Backend, pos: TilePos) {
+		use crate::BackendStyle;
+		let tile = self.grid.tile(pos).unwrap();
+		let materials = self.grid.materials();
+		backend.draw_to_background(materials[tile.floor].sprite, pos);
+		let sprite = match tile.structure {
+			Some(Material::PLATFORM) => Some((2, self.platform_variant(pos, &HashSet::default()))),
+			Some(Material::CONVEYOR) => Some(self.conveyor_sprite(pos)),
+			Some(mat) => Some(materials[mat].sprite),
+			None => None,
+		};
+		if let Some(sprite) = sprite {
+			backend.draw_to_background(sprite, pos);
+		}
+		if !self.debug_mode {
+			backend.tint_background(pos, self.lighting.tint(pos));
+		}
+	}
+
+	/// The auto-tiling variant of a Platform at `pos`, which connects to the neighboring
+	/// Platforms, Machines and `ghosts`
+	pub fn platform_variant(&self, pos: TilePos, ghosts: &HashSet<TilePos>) -> usize {
+		Dir::all()
+			.map(|dir| {
+				self.grid
+					.tile_in_dir(pos, dir)
+					.map(|p| {
+						ghosts.contains(&p)
+							|| matches!(
+								self.grid.get(p),
+								Some(Material::PLATFORM) | Some(Material::MACHINE)
+							)
+					})
+					.unwrap_or(false) as usize
+			})
+			.rfold(0, |prev, cur| (prev << 1) | cur)
+	}
+
+	/// The direction that the Conveyor at `pos` moves Items towards
+	pub fn conveyor_dir(&self, pos: TilePos) -> Option<Dir> {
+		self.machine_at(pos)
+			.filter(|m| m.machine_type == MachineType::Conveyor)
+			.map(|m| m.rotation)
+	}
+	/// Picks the Conveyor sprite at `pos`. It bends if a single Conveyor feeds into it from the side
+	fn conveyor_sprite(&self, pos: TilePos) -> (usize, usize) {
+		let dir = self.conveyor_dir(pos).unwrap_or(Dir::Up);
+		let feeds = |from: Dir| {
+			self.grid
+				.tile_in_dir(pos, from)
+				.and_then(|p| self.conveyor_dir(p))
+				.map(|d| d == from.opposite())
+				.unwrap_or(false)
+		};
+		let from = match (
+			feeds(dir.opposite()),
+			feeds(dir.counter_clockwise()),
+			feeds(dir.clockwise()),
+		) {
+			(false, true, false) => dir.counter_clockwise(),
+			(false, false, true) => dir.clockwise(),
+			_ => dir.opposite(),
+		};
+		super::conveyor_sprite(dir, from)
+	}
+
+	/// Returns the Products that Machines finished in this Tick
+	pub fn update(&mut self, spawn_has_power: bool) -> Vec<(TilePos, Product)> {
 		{
 			let mut source_change = vec![];
 			for machine in self.machines.values() {
@@ -265,29 +552,113 @@ impl World {
 				self.machine_at_mut(pos).unwrap().set_power_source(change);
 			}
 		}
+		// Water around a Machine short-circuits it
+		let flooded: HashSet<TilePos> = self
+			.machines
+			.values()
+			.filter(|m| !m.machine_type.is_dragged())
+			.filter(|m| m.border(self).iter().any(|t| self[*t] == Material::WATER))
+			.map(|m| m.pos)
+			.collect();
+		let mut products = vec![];
 		for machine in self.machines.values_mut() {
-			machine.update(spawn_has_power);
+			let flooded = flooded.contains(&machine.pos);
+			if let Some(product) = machine.update(spawn_has_power, flooded) {
+				products.push((machine.pos, product));
+			}
 		}
 		if !self.changes.is_empty() {
 			let tiles: Vec<_> = self.changes.iter().map(|p| (*p).into()).collect();
 			self.changes.clear();
 			self.hpa_map.tiles_changed(&tiles, self.grid.cost_fn());
 		}
+		products
 	}
 
 	pub fn add_machine<T: Into<TilePos>>(&mut self, pos: T, machine: MachineType) {
+		self.add_rotated_machine(pos, machine, Dir::Up);
+	}
+	/// Adds a Machine with its top left corner at `pos`
+	pub fn add_rotated_machine<T: Into<TilePos>>(
+		&mut self,
+		pos: T,
+		machine: MachineType,
+		rotation: Dir,
+	) {
 		let pos: TilePos = pos.into();
-		if machine == MachineType::Spawn {
+		let machine = Machine::new(pos, machine, rotation);
+		for tile in machine.tiles() {
+			self.set(tile, machine.machine_type.material());
+		}
+		for tile in machine.tiles() {
+			self.machine_tiles.insert(tile, pos);
+		}
+		if machine.machine_type == MachineType::Spawn {
 			self.spawns.insert(pos);
 		}
-		self.set(pos, Material::Machine);
-		self.machines.insert(pos, Machine::new(pos, machine));
+		self.machines.insert(pos, machine);
+	}
+	pub fn take_visual_changes(&mut self) -> HashSet<TilePos> {
+		std::mem::take(&mut self.visual_changes)
+	}
+
+	pub fn machines(&self) -> impl Iterator<Item = &Machine> {
+		self.machines.values()
+	}
+	pub fn machines_mut(&mut self) -> impl Iterator<Item = &mut Machine> {
+		self.machines.values_mut()
+	}
+	/// Returns the Minerals that were spilled by removed Machines since the last call
+	pub fn take_spilled(&mut self) -> Vec<(TilePos, Mineral)> {
+		std::mem::take(&mut self.spilled)
+	}
+
+	/// The amount of `mineral` in all storages
+	pub fn stock(&self, mineral: Mineral) -> usize {
+		self.machines().map(|m| m.stock(mineral)).sum()
+	}
+	/// Removes `amount` of `mineral` from the stock. Returns `false` if there is not enough
+	pub fn spend_mineral(&mut self, mineral: Mineral, amount: usize) -> bool {
+		if self.stock(mineral) < amount {
+			return false;
+		}
+		let mut remaining = amount;
+		for machine in self.machines_mut() {
+			remaining -= machine.withdraw(mineral, remaining);
+		}
+		self.stock_changes.push((mineral, -(amount as isize)));
+		true
+	}
+	/// Puts `amount` of `mineral` back into storage. What doesn't fit is spilled at `pos`
+	pub fn refund_mineral(&mut self, pos: TilePos, mineral: Mineral, amount: usize) {
+		let mut stored = 0;
+		for machine in self.machines.values_mut() {
+			while stored < amount && machine.deposit(mineral) {
+				stored += 1;
+			}
+		}
+		if stored > 0 {
+			self.stock_changes.push((mineral, stored as isize));
+		}
+		for _ in stored..amount {
+			self.spilled.push((pos, mineral));
+		}
+	}
+	/// Returns the Minerals that were spent or refunded since the last call
+	pub fn take_stock_changes(&mut self) -> Vec<(Mineral, isize)> {
+		std::mem::take(&mut self.stock_changes)
+	}
+	pub fn spawns(&self) -> impl Iterator<Item = TilePos> + '_ {
+		self.spawns.iter().copied()
 	}
+	/// The Machine that covers `pos`
 	pub fn machine_at(&self, pos: TilePos) -> Option<&Machine> {
-		self.machines.get(&pos)
+		let origin = self.machine_tiles.get(&pos)?;
+		self.machines.get(origin)
 	}
 	pub fn machine_at_mut(&mut self, pos: TilePos) -> Option<&mut Machine> {
-		self.machines.get_mut(&pos)
+		let origin = self.machine_tiles.get(&pos)?;
+		self.machines.get_mut(origin)
 	}
 
 	pub fn path(&mut self, start: TilePos, end: TilePos) -> Option<Path> {